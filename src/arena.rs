@@ -1,7 +1,17 @@
-use crate::{animation::FlashingTimer, constants::*};
+use std::{error::Error, fs, path::Path};
+
+use crate::{
+    animation::FlashingTimer,
+    ball::{Ball, LastTouch},
+    constants::*,
+    mode::GameMode,
+    obstacle::Obstacle,
+    save::{write_save_data, SaveData},
+    simulation::GameplaySet,
+};
 use bevy::{app::AppExit, prelude::*, sprite::MaterialMesh2dBundle};
-use bevy_pkv::PkvStore;
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub struct ArenaPlugin;
 
@@ -9,15 +19,25 @@ impl Plugin for ArenaPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<GoalEvent>()
             .add_systems(Startup, (setup_ground, setup_goals, setup_walls))
-            .add_systems(Update, (touch_goal, score_goal, update_high_score))
-            .add_systems(Last, clear_score);
+            .add_systems(
+                FixedUpdate,
+                (
+                    touch_goal,
+                    // Penalties are kept on their own scoreboard.
+                    score_goal.run_if(not(in_state(GameMode::Shootout))),
+                )
+                    .in_set(GameplaySet),
+            )
+            .add_systems(Last, clear_score.before(write_save_data));
     }
 }
 
-#[derive(Event, Debug)]
+#[derive(Event, Clone, Debug)]
 pub struct GoalEvent {
     pub score_amount: i32,
     pub goal: Entity,
+    /// The player who last touched the ball.
+    pub scorer: Option<Entity>,
 }
 
 #[derive(Component)]
@@ -26,28 +46,61 @@ pub struct Ground;
 #[derive(Component)]
 pub struct Goal;
 
-const GOAL_HEIGHT: f32 = 160.;
-const GOAL_WIDTH: f32 = 20.;
+/// The team that scores by putting the ball in the goal at `translation`.
+/// Team 0 kicks off on the left, so it attacks the goal on the right.
+pub(crate) fn attacking_team(translation: Vec3) -> usize {
+    if translation.x > 0. {
+        0
+    } else {
+        1
+    }
+}
+
+pub(crate) const GOAL_HEIGHT: f32 = 160.;
+pub(crate) const GOAL_WIDTH: f32 = 20.;
 
 const GOAL_POSITIONS: [Vec3; 2] = [
-    Vec3::new(-WINDOW_WIDTH / 2., GROUND_MIDDLE, 1.), // middle left
-    Vec3::new(WINDOW_WIDTH / 2., GROUND_MIDDLE, 1.),  // middle right
+    Vec3::new(-VIRTUAL_WIDTH / 2., GROUND_MIDDLE, 1.), // middle left
+    Vec3::new(VIRTUAL_WIDTH / 2., GROUND_MIDDLE, 1.),  // middle right
 ];
 
+/// Where the goals go unless an arena file moves them.
+pub(crate) fn default_goals() -> Vec<GoalPlacement> {
+    GOAL_POSITIONS
+        .iter()
+        .map(|&translation| GoalPlacement {
+            translation,
+            rotation: 0.,
+        })
+        .collect()
+}
+
 fn setup_goals(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    for position in GOAL_POSITIONS.iter() {
-        commands.spawn((
+    for placement in &default_goals() {
+        spawn_goal(&mut commands, &mut meshes, &mut materials, placement);
+    }
+}
+
+pub(crate) fn spawn_goal(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    placement: &GoalPlacement,
+) -> Entity {
+    commands
+        .spawn((
             MaterialMesh2dBundle {
                 mesh: meshes
                     .add(Rectangle {
                         half_size: Vec2::new(GOAL_WIDTH / 2., GOAL_HEIGHT / 2.), // (width, height)
                     })
                     .into(),
-                transform: Transform::from_translation(*position),
+                transform: Transform::from_translation(placement.translation)
+                    .with_rotation(Quat::from_rotation_z(placement.rotation)),
                 material: materials.add(Color::PINK),
                 ..default()
             },
@@ -55,7 +108,40 @@ fn setup_goals(
             RigidBody::Fixed,
             Collider::cuboid(GOAL_WIDTH / 2., GOAL_HEIGHT / 2.),
             Sensor,
-        ));
+        ))
+        .id()
+}
+
+/// Where a goal sits in an arena file.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub(crate) struct GoalPlacement {
+    pub(crate) translation: Vec3,
+    /// Radians around the z axis.
+    #[serde(default)]
+    pub(crate) rotation: f32,
+}
+
+/// An arena as saved by the level editor, stored as RON.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct ArenaFile {
+    pub(crate) goals: Vec<GoalPlacement>,
+    pub(crate) obstacles: Vec<Obstacle>,
+}
+
+impl ArenaFile {
+    pub(crate) fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        Ok(ron::from_str(&contents)?)
+    }
+
+    pub(crate) fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, contents)?;
+        Ok(())
     }
 }
 
@@ -63,13 +149,16 @@ fn touch_goal(
     mut commands: Commands,
     mut collision_events: EventReader<CollisionEvent>,
     goals: Query<Entity, With<Goal>>,
+    balls: Query<Entity, With<Ball>>,
+    last_touch: Res<LastTouch>,
     mut event_writer: EventWriter<GoalEvent>,
 ) {
     for collision_event in collision_events.read() {
         if let CollisionEvent::Started(entity1, entity2, _flags) = collision_event {
-            for goal in &goals {
-                if goal == *entity1 {
-                    let mut entity = commands.entity(*entity1);
+            // Rapier doesn't promise which way round the pair comes.
+            for (goal, ball) in [(*entity1, *entity2), (*entity2, *entity1)] {
+                if goals.contains(goal) && balls.contains(ball) {
+                    let mut entity = commands.entity(goal);
                     entity.insert(FlashingTimer(Timer::from_seconds(
                         0.1,
                         TimerMode::Repeating,
@@ -77,6 +166,7 @@ fn touch_goal(
                     event_writer.send(GoalEvent {
                         score_amount: 1,
                         goal,
+                        scorer: last_touch.0,
                     });
                 }
             }
@@ -84,25 +174,9 @@ fn touch_goal(
     }
 }
 
-fn score_goal(mut goal_events: EventReader<GoalEvent>, mut pkv: ResMut<PkvStore>) {
+fn score_goal(mut goal_events: EventReader<GoalEvent>, mut save: ResMut<SaveData>) {
     for goal_event in goal_events.read() {
-        if let Ok(mut score) = pkv.get::<i32>("score") {
-            score += goal_event.score_amount;
-            pkv.set("score", &score).expect("Failed to set score");
-        } else {
-            let score = goal_event.score_amount;
-            pkv.set("score", &score).expect("Failed to set score");
-        }
-    }
-}
-
-fn update_high_score(mut pkv: ResMut<PkvStore>) {
-    let score = pkv.get::<i32>("score").unwrap_or(0);
-    let high_score = pkv.get::<i32>("high_score").unwrap_or(0);
-
-    if score > high_score {
-        pkv.set("high_score", &score)
-            .expect("Failed to set high score");
+        save.score += goal_event.score_amount;
     }
 }
 
@@ -130,8 +204,8 @@ pub struct Wall;
 fn setup_walls(mut commands: Commands) {
     // Vertical walls
     for (x, y) in [
-        (WINDOW_WIDTH / 2., GROUND_MIDDLE),
-        (-WINDOW_WIDTH / 2., GROUND_MIDDLE),
+        (VIRTUAL_WIDTH / 2., GROUND_MIDDLE),
+        (-VIRTUAL_WIDTH / 2., GROUND_MIDDLE),
     ] {
         commands.spawn((
             SpriteBundle {
@@ -151,28 +225,30 @@ fn setup_walls(mut commands: Commands) {
 
     // Horizontal walls
     for (x, y) in [
-        (0., WINDOW_HEIGHT / 2.),
-        (0., (-WINDOW_HEIGHT / 2.) + GROUND_OFFSET.y * 2.),
+        (0., VIRTUAL_HEIGHT / 2.),
+        (0., (-VIRTUAL_HEIGHT / 2.) + GROUND_OFFSET.y * 2.),
     ] {
         commands.spawn((
             SpriteBundle {
                 transform: Transform::from_translation(Vec3::new(x, y, 0.)),
                 sprite: Sprite {
                     color: Color::RED,
-                    custom_size: Some(Vec2::new(WINDOW_WIDTH, 2.)),
+                    custom_size: Some(Vec2::new(VIRTUAL_WIDTH, 2.)),
                     ..default()
                 },
                 ..default()
             },
             Wall,
             RigidBody::Fixed,
-            Collider::cuboid(WINDOW_WIDTH / 2., 1.),
+            Collider::cuboid(VIRTUAL_WIDTH / 2., 1.),
         ));
     }
 }
 
-fn clear_score(mut pkv: ResMut<PkvStore>, mut events: EventReader<AppExit>) {
+/// The next launch starts from nil-nil. An unfinished match keeps its score
+/// in `SaveData::suspended`.
+pub(crate) fn clear_score(mut save: ResMut<SaveData>, mut events: EventReader<AppExit>) {
     for _ in events.read() {
-        pkv.set("score", &0).expect("Failed to set score");
+        save.score = 0;
     }
 }
@@ -7,14 +7,24 @@ use bevy_rapier2d::prelude::*;
 use crate::{
     arena::{GoalEvent, Wall},
     constants::*,
+    player::Player,
+    simulation::{GameplaySet, Interpolated},
 };
 
 pub struct BallPlugin;
 
 impl Plugin for BallPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, spawn_ball.run_if(run_if_no_ball))
-            .add_systems(FixedUpdate, (hit_walls, despawn_after_goal));
+        app.init_resource::<LastTouch>().add_systems(
+            FixedUpdate,
+            (
+                spawn_ball.run_if(run_if_no_ball),
+                track_last_touch,
+                hit_walls,
+                despawn_after_goal,
+            )
+                .in_set(GameplaySet),
+        );
     }
 }
 
@@ -25,7 +35,11 @@ fn run_if_no_ball(balls: Query<Entity, With<Ball>>) -> bool {
 #[derive(Component)]
 pub struct Ball;
 
-const BALL_RADIUS: f32 = 15.;
+/// The player who last touched the ball, if anyone has since kickoff.
+#[derive(Resource, Default)]
+pub struct LastTouch(pub Option<Entity>);
+
+pub(crate) const BALL_RADIUS: f32 = 15.;
 
 fn spawn_ball(
     mut commands: Commands,
@@ -35,15 +49,18 @@ fn spawn_ball(
     let mesh = Mesh2dHandle(meshes.add(Circle {
         radius: BALL_RADIUS,
     }));
+    let transform = Transform::from_translation(Vec3::new(0., GROUND_MIDDLE, 1.));
     commands.spawn((
         MaterialMesh2dBundle {
             mesh,
             material: materials.add(Color::ORANGE),
-            transform: Transform::from_translation(Vec3::new(0., GROUND_MIDDLE, 1.)),
+            transform,
             ..default()
         },
         Ball,
+        Interpolated::new(transform),
         RigidBody::Dynamic,
+        Velocity::zero(),
         AdditionalMassProperties::Mass(1.0),
         Collider::ball(BALL_RADIUS),
         Friction {
@@ -79,14 +96,33 @@ fn hit_walls(
     }
 }
 
+pub(crate) fn track_last_touch(
+    mut collision_events: EventReader<CollisionEvent>,
+    balls: Query<Entity, With<Ball>>,
+    players: Query<Entity, With<Player>>,
+    mut last_touch: ResMut<LastTouch>,
+) {
+    for collision_event in collision_events.read() {
+        if let CollisionEvent::Started(entity1, entity2, _flags) = collision_event {
+            for (ball, player) in [(*entity1, *entity2), (*entity2, *entity1)] {
+                if balls.contains(ball) && players.contains(player) {
+                    last_touch.0 = Some(player);
+                }
+            }
+        }
+    }
+}
+
 fn despawn_after_goal(
     mut commands: Commands,
     mut goal_events: EventReader<GoalEvent>,
     balls: Query<Entity, With<Ball>>,
+    mut last_touch: ResMut<LastTouch>,
 ) {
     for _ in goal_events.read() {
         for ball in &balls {
             commands.entity(ball).despawn()
         }
+        last_touch.0 = None;
     }
 }
@@ -1,77 +1,476 @@
-use crate::constants::*;
+use crate::{
+    ball::Ball,
+    constants::*,
+    mode::GameMode,
+    player::{Player, PlayerType, Team, Versus, NUM_TEAMS},
+};
 use bevy::{
     prelude::*,
     render::{
-        camera::RenderTarget,
+        camera::{RenderTarget, Viewport},
         render_resource::{
             Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
         },
         view::RenderLayers,
     },
+    window::{PrimaryWindow, WindowMode},
 };
+use bevy_rapier2d::prelude::*;
 
 pub struct CameraPlugin;
 
 #[derive(Component)]
-struct MainCamera;
+pub struct MainCamera;
+
+/// Where the main camera is looking, kept apart from its `Transform` so
+/// effects can be layered on without feeding back into the follow smoothing.
+#[derive(Component)]
+pub struct CameraRig {
+    pub focus: Vec2,
+    pub zoom: f32,
+    /// Screen shake, added after the view is clamped to the bounds.
+    pub shake_offset: Vec2,
+    pub shake_angle: f32,
+    /// How far (0 to 1) to pull the view in on `punch_focus`.
+    pub punch: f32,
+    pub punch_focus: Vec2,
+}
+
+impl Default for CameraRig {
+    fn default() -> Self {
+        Self {
+            focus: Vec2::ZERO,
+            zoom: 1.,
+            shake_offset: Vec2::ZERO,
+            shake_angle: 0.,
+            punch: 0.,
+            punch_focus: Vec2::ZERO,
+        }
+    }
+}
 
+/// Draws the HUD over the main camera's view. It only renders `UI_LAYER`, so
+/// zooming or shaking the pitch leaves the HUD where it is.
 #[derive(Component)]
-struct UICamera;
+pub struct UICamera;
 
 pub const UI_LAYER: RenderLayers = RenderLayers::layer(9);
 
+/// One half of the screen in split-screen play, following the live player on
+/// `team`.
+#[derive(Component)]
+pub struct SplitCamera {
+    pub team: usize,
+    focus: Vec2,
+}
+
+/// Whether local versus matches give each human their own half of the screen.
+/// `F3` toggles it, when there is a second human.
+#[derive(Resource, Default)]
+pub struct SplitScreen {
+    pub enabled: bool,
+}
+
+/// Only the upscaled canvas lives on this layer.
+const CANVAS_LAYER: RenderLayers = RenderLayers::layer(8);
+
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (spawn_main_camera, spawn_ui_camera));
+        app.init_resource::<CameraBounds>()
+            .init_resource::<Canvas>()
+            .init_resource::<SplitScreen>()
+            .add_systems(
+                Startup,
+                (spawn_main_camera, spawn_ui_camera, spawn_window_camera),
+            )
+            .add_systems(
+                Update,
+                (
+                    (follow_ball, apply_camera_rig).chain(),
+                    (
+                        toggle_split_screen.run_if(
+                            in_state(GameMode::Match).and_then(|versus: Res<Versus>| versus.0),
+                        ),
+                        spawn_split_cameras,
+                        follow_live_players,
+                        merge_split_cameras,
+                    )
+                        .chain(),
+                    fit_canvas,
+                    toggle_fullscreen,
+                ),
+            );
+    }
+}
+
+/// The game and HUD are drawn into this `VIRTUAL_WIDTH` x `VIRTUAL_HEIGHT`
+/// image, which is then shown scaled up by a whole number to fit the window,
+/// with black bars filling whatever is left over.
+#[derive(Resource)]
+pub struct Canvas {
+    pub image: Handle<Image>,
+    /// Logical window pixels per canvas pixel.
+    pub scale: f32,
+}
+
+impl FromWorld for Canvas {
+    fn from_world(world: &mut World) -> Self {
+        let size = Extent3d {
+            width: VIRTUAL_WIDTH as u32,
+            height: VIRTUAL_HEIGHT as u32,
+            ..default()
+        };
+
+        let mut image = Image {
+            texture_descriptor: TextureDescriptor {
+                label: None,
+                size,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Bgra8UnormSrgb,
+                mip_level_count: 1,
+                sample_count: 1,
+                usage: TextureUsages::RENDER_ATTACHMENT
+                    | TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            ..default()
+        };
+        image.resize(size);
+
+        Self {
+            image: world.resource_mut::<Assets<Image>>().add(image),
+            scale: 1.,
+        }
+    }
+}
+
+impl Canvas {
+    /// Converts a cursor position in the window to one on the canvas, which
+    /// is what the main camera's viewport is measured in.
+    pub fn cursor_position(&self, window: &Window) -> Option<Vec2> {
+        let cursor = window.cursor_position()?;
+        let window_centre = Vec2::new(window.width(), window.height()) / 2.;
+        let position = (cursor - window_centre) / self.scale + VIEW_SIZE / 2.;
+
+        (position.cmpge(Vec2::ZERO).all() && position.cmple(VIEW_SIZE).all()).then_some(position)
     }
 }
 
-fn spawn_main_camera(mut commands: Commands) {
+#[derive(Component)]
+struct CanvasSprite;
+
+/// The part of the world the main camera is allowed to show.
+#[derive(Resource)]
+pub struct CameraBounds(pub Rect);
+
+impl Default for CameraBounds {
+    fn default() -> Self {
+        Self(Rect::from_center_size(
+            Vec2::ZERO,
+            Vec2::new(VIRTUAL_WIDTH, VIRTUAL_HEIGHT),
+        ))
+    }
+}
+
+const VIEW_SIZE: Vec2 = Vec2::new(VIRTUAL_WIDTH, VIRTUAL_HEIGHT);
+const ATTACK_DIRECTION: Vec2 = Vec2::X;
+const LOOK_AHEAD: f32 = 60.;
+const VELOCITY_LOOK_AHEAD: f32 = 0.25; // seconds of ball travel
+const FOLLOW_RATE: f32 = 4.;
+const ZOOM_RATE: f32 = 2.;
+const ZOOM_MARGIN: f32 = 120.;
+const MIN_ZOOM: f32 = 0.6;
+const PUNCH_ZOOM: f32 = 0.35;
+const SPLIT_VIEW_SIZE: Vec2 = Vec2::new(VIRTUAL_WIDTH / 2., VIRTUAL_HEIGHT);
+// The halves merge back into one view once the players are this close, and
+// only split again a little further out so the screen doesn't flicker.
+const MERGE_DISTANCE: f32 = 250.;
+const SPLIT_DISTANCE: f32 = 300.;
+
+fn spawn_main_camera(mut commands: Commands, canvas: Res<Canvas>) {
     commands.spawn((
         Camera2dBundle {
+            camera: Camera {
+                order: -2,
+                target: RenderTarget::Image(canvas.image.clone()),
+                ..default()
+            },
             transform: Transform::from_translation(Vec3::new(0., 0., 10.)),
             ..default()
         },
         MainCamera,
+        CameraRig::default(),
     ));
 }
 
-fn spawn_ui_camera(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
-    let canvas_size = Extent3d {
-        width: WINDOW_WIDTH as u32,
-        height: WINDOW_HEIGHT as u32,
-        ..default()
-    };
-
-    let mut canvas = Image {
-        texture_descriptor: TextureDescriptor {
-            label: None,
-            size: canvas_size,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Bgra8UnormSrgb,
-            mip_level_count: 1,
-            sample_count: 1,
-            usage: TextureUsages::RENDER_ATTACHMENT
-                | TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_DST,
-            view_formats: &[],
-        },
-        ..default()
-    };
-
-    canvas.resize(canvas_size);
-    let image_handle = images.add(canvas);
-
+fn spawn_ui_camera(mut commands: Commands, canvas: Res<Canvas>) {
     commands.spawn((
         Camera2dBundle {
             camera: Camera {
                 order: -1,
-                target: RenderTarget::Image(image_handle.clone()),
+                target: RenderTarget::Image(canvas.image.clone()),
+                clear_color: ClearColorConfig::None,
                 ..default()
             },
             ..default()
         },
         UICamera,
         UI_LAYER,
+        IsDefaultUiCamera,
     ));
 }
+
+/// Shows the finished canvas in the window.
+fn spawn_window_camera(mut commands: Commands, canvas: Res<Canvas>) {
+    commands.spawn((
+        SpriteBundle {
+            texture: canvas.image.clone(),
+            ..default()
+        },
+        CanvasSprite,
+        CANVAS_LAYER,
+    ));
+    commands.spawn((Camera2dBundle::default(), CANVAS_LAYER));
+}
+
+/// Scales the canvas by the largest whole number of physical pixels that fits
+/// the window, so every canvas pixel stays the same size. Windows smaller
+/// than the canvas get a plain shrink instead.
+fn fit_canvas(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut canvas: ResMut<Canvas>,
+    mut sprites: Query<&mut Transform, With<CanvasSprite>>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    let physical = Vec2::new(
+        window.physical_width() as f32,
+        window.physical_height() as f32,
+    );
+    let fit = (physical / VIEW_SIZE).min_element();
+    let scale = if fit >= 1. { fit.floor() } else { fit } / window.scale_factor();
+
+    if canvas.scale != scale {
+        canvas.scale = scale;
+    }
+    for mut transform in &mut sprites {
+        transform.scale = Vec3::new(scale, scale, 1.);
+    }
+}
+
+fn toggle_fullscreen(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !keys.just_pressed(KeyCode::F11) {
+        return;
+    }
+
+    for mut window in &mut windows {
+        window.mode = match window.mode {
+            WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+            _ => WindowMode::Windowed,
+        };
+    }
+}
+
+/// Eases the camera toward a point a little ahead of the ball, zooming out
+/// to keep every player in shot but never past the edge of `CameraBounds`.
+fn follow_ball(
+    time: Res<Time>,
+    bounds: Res<CameraBounds>,
+    balls: Query<(&Transform, Option<&Velocity>), With<Ball>>,
+    players: Query<&Transform, With<Player>>,
+    mut rigs: Query<&mut CameraRig, With<MainCamera>>,
+) {
+    let Ok(mut rig) = rigs.get_single_mut() else {
+        return;
+    };
+    let Ok((ball, velocity)) = balls.get_single() else {
+        return;
+    };
+
+    let ball_position = ball.translation.truncate();
+    let velocity = velocity.map_or(Vec2::ZERO, |velocity| velocity.linvel);
+    let target = ball_position + ATTACK_DIRECTION * LOOK_AHEAD + velocity * VELOCITY_LOOK_AHEAD;
+
+    let (min, max) = players
+        .iter()
+        .map(|player| player.translation.truncate())
+        .fold((ball_position, ball_position), |(min, max), position| {
+            (min.min(position), max.max(position))
+        });
+    let max_zoom = (bounds.0.size() / VIEW_SIZE).min_element().max(MIN_ZOOM);
+    let target_zoom = ((max - min + ZOOM_MARGIN) / VIEW_SIZE)
+        .max_element()
+        .clamp(MIN_ZOOM, max_zoom);
+
+    let blend = |rate: f32| 1. - (-rate * time.delta_seconds()).exp();
+    rig.zoom += (target_zoom - rig.zoom) * blend(ZOOM_RATE);
+
+    let focus = rig.focus.lerp(target, blend(FOLLOW_RATE));
+    rig.focus = clamp_to_bounds(focus, VIEW_SIZE * rig.zoom / 2., bounds.0);
+}
+
+pub(crate) fn apply_camera_rig(
+    bounds: Res<CameraBounds>,
+    mut cameras: Query<(&CameraRig, &mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    for (rig, mut transform, mut projection) in &mut cameras {
+        let zoom = rig.zoom * (1. - PUNCH_ZOOM * rig.punch);
+        let focus = rig.focus.lerp(rig.punch_focus, rig.punch);
+        let position = clamp_to_bounds(focus, VIEW_SIZE * zoom / 2., bounds.0) + rig.shake_offset;
+
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        transform.rotation = Quat::from_rotation_z(rig.shake_angle);
+        projection.scale = zoom;
+    }
+}
+
+/// Keeps a view of `half_view` around `centre` inside `bounds`, centring it
+/// on any axis where the view is already bigger than the bounds.
+fn clamp_to_bounds(centre: Vec2, half_view: Vec2, bounds: Rect) -> Vec2 {
+    let min = bounds.min + half_view;
+    let max = bounds.max - half_view;
+    let axis = |centre: f32, min: f32, max: f32, middle: f32| {
+        if min > max {
+            middle
+        } else {
+            centre.clamp(min, max)
+        }
+    };
+
+    Vec2::new(
+        axis(centre.x, min.x, max.x, bounds.center().x),
+        axis(centre.y, min.y, max.y, bounds.center().y),
+    )
+}
+
+fn toggle_split_screen(keys: Res<ButtonInput<KeyCode>>, mut split_screen: ResMut<SplitScreen>) {
+    if keys.just_pressed(KeyCode::F3) {
+        split_screen.enabled = !split_screen.enabled;
+    }
+}
+
+/// Gives each team a camera on its own half of the canvas while split screen
+/// is on, and removes them again when it is switched off.
+fn spawn_split_cameras(
+    mut commands: Commands,
+    canvas: Res<Canvas>,
+    split_screen: Res<SplitScreen>,
+    cameras: Query<Entity, With<SplitCamera>>,
+) {
+    if !split_screen.is_changed() {
+        return;
+    }
+
+    for entity in &cameras {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if !split_screen.enabled {
+        return;
+    }
+
+    for team in 0..NUM_TEAMS {
+        let width = SPLIT_VIEW_SIZE.x as u32;
+        commands.spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    // Starts inactive; `merge_split_cameras` decides whether
+                    // the players are far enough apart to need it.
+                    is_active: false,
+                    order: -4 + team as isize,
+                    target: RenderTarget::Image(canvas.image.clone()),
+                    viewport: Some(Viewport {
+                        physical_position: UVec2::new(width * team as u32, 0),
+                        physical_size: UVec2::new(width, SPLIT_VIEW_SIZE.y as u32),
+                        ..default()
+                    }),
+                    // Clearing affects the whole canvas, so only the first
+                    // half may do it.
+                    clear_color: if team == 0 {
+                        ClearColorConfig::Default
+                    } else {
+                        ClearColorConfig::None
+                    },
+                    ..default()
+                },
+                transform: Transform::from_translation(Vec3::new(0., 0., 10.)),
+                ..default()
+            },
+            SplitCamera {
+                team,
+                focus: Vec2::ZERO,
+            },
+        ));
+    }
+}
+
+fn follow_live_players(
+    time: Res<Time>,
+    bounds: Res<CameraBounds>,
+    players: Query<(&Team, &PlayerType, &Transform), Without<SplitCamera>>,
+    mut cameras: Query<(&mut SplitCamera, &mut Transform)>,
+) {
+    let blend = 1. - (-FOLLOW_RATE * time.delta_seconds()).exp();
+
+    for (mut camera, mut transform) in &mut cameras {
+        let Some((_, _, player)) = players.iter().find(|(team, player_type, _)| {
+            team.0 == camera.team && **player_type == PlayerType::Live
+        }) else {
+            continue;
+        };
+
+        let focus = camera.focus.lerp(player.translation.truncate(), blend);
+        camera.focus = clamp_to_bounds(focus, SPLIT_VIEW_SIZE / 2., bounds.0);
+        transform.translation.x = camera.focus.x;
+        transform.translation.y = camera.focus.y;
+    }
+}
+
+/// Swaps between the split cameras and the shared main camera depending on
+/// how far apart the live players are. Outside a match there is only ever
+/// the main camera.
+fn merge_split_cameras(
+    split_screen: Res<SplitScreen>,
+    mode: Res<State<GameMode>>,
+    players: Query<(&PlayerType, &Transform)>,
+    mut main_cameras: Query<&mut Camera, (With<MainCamera>, Without<SplitCamera>)>,
+    mut split_cameras: Query<&mut Camera, With<SplitCamera>>,
+) {
+    let Ok(mut main_camera) = main_cameras.get_single_mut() else {
+        return;
+    };
+
+    let live: Vec<Vec2> = players
+        .iter()
+        .filter(|(player_type, _)| **player_type == PlayerType::Live)
+        .map(|(_, transform)| transform.translation.truncate())
+        .collect();
+    let spread = match live.as_slice() {
+        [first, second, ..] => first.distance(*second),
+        _ => 0.,
+    };
+
+    let split = split_screen.enabled
+        && *mode.get() == GameMode::Match
+        && if main_camera.is_active {
+            spread > SPLIT_DISTANCE
+        } else {
+            spread > MERGE_DISTANCE
+        };
+
+    if main_camera.is_active == split {
+        main_camera.is_active = !split;
+    }
+    for mut camera in &mut split_cameras {
+        if camera.is_active != split {
+            camera.is_active = split;
+        }
+    }
+}
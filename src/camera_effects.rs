@@ -0,0 +1,175 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+
+use crate::{
+    arena::GoalEvent,
+    ball::Ball,
+    camera::{apply_camera_rig, CameraRig, MainCamera},
+    player::Player,
+};
+
+pub struct CameraEffectsPlugin;
+
+impl Plugin for CameraEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Trauma>()
+            .init_resource::<HitStop>()
+            .init_resource::<Celebration>()
+            .add_systems(
+                Update,
+                (
+                    (ball_impacts, start_celebration),
+                    (shake_camera, end_hit_stop, celebrate_goal),
+                )
+                    .chain()
+                    .before(apply_camera_rig),
+            );
+    }
+}
+
+/// How hard the screen is shaking, from 0 to 1. The shake grows with the
+/// square of it, so small knocks barely register and big ones really do.
+#[derive(Resource, Default)]
+pub struct Trauma(pub f32);
+
+impl Trauma {
+    pub fn add(&mut self, amount: f32) {
+        self.0 = (self.0 + amount).min(1.);
+    }
+}
+
+/// Counts down in real time while the game clock is slowed for a hit-stop.
+#[derive(Resource, Default)]
+struct HitStop(Option<Timer>);
+
+#[derive(Resource, Default)]
+struct Celebration {
+    timer: Option<Timer>,
+    scorer: Option<Entity>,
+    /// Used when nobody touched the ball before it went in.
+    goal: Vec2,
+}
+
+const TRAUMA_DECAY: f32 = 1.5; // per second
+const MAX_SHAKE_OFFSET: f32 = 12.;
+const MAX_SHAKE_ANGLE: f32 = 0.05; // radians
+const HARD_IMPACT_SPEED: f32 = 350.;
+const POWER_SHOT_SPEED: f32 = 450.;
+const HIT_STOP_SECONDS: f32 = 0.08;
+const HIT_STOP_SPEED: f32 = 0.05;
+const GOAL_TRAUMA: f32 = 0.6;
+const CELEBRATION_SECONDS: f32 = 1.5;
+
+/// Shakes the screen when the ball hits anything fast, and briefly freezes
+/// the game when a player sends it away at shot speed.
+fn ball_impacts(
+    mut collision_events: EventReader<CollisionEvent>,
+    balls: Query<&Velocity, With<Ball>>,
+    players: Query<(), With<Player>>,
+    mut trauma: ResMut<Trauma>,
+    mut hit_stop: ResMut<HitStop>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(entity1, entity2, _flags) = collision_event else {
+            continue;
+        };
+
+        for (ball, other) in [(*entity1, *entity2), (*entity2, *entity1)] {
+            let Ok(velocity) = balls.get(ball) else {
+                continue;
+            };
+            let speed = velocity.linvel.length();
+
+            if speed > HARD_IMPACT_SPEED {
+                trauma.add(((speed - HARD_IMPACT_SPEED) / HARD_IMPACT_SPEED).clamp(0.1, 0.5));
+            }
+
+            if speed > POWER_SHOT_SPEED && players.contains(other) && hit_stop.0.is_none() {
+                time.set_relative_speed(HIT_STOP_SPEED);
+                hit_stop.0 = Some(Timer::from_seconds(HIT_STOP_SECONDS, TimerMode::Once));
+            }
+        }
+    }
+}
+
+fn start_celebration(
+    mut goal_events: EventReader<GoalEvent>,
+    transforms: Query<&Transform>,
+    mut trauma: ResMut<Trauma>,
+    mut celebration: ResMut<Celebration>,
+) {
+    for goal_event in goal_events.read() {
+        trauma.add(GOAL_TRAUMA);
+
+        celebration.scorer = goal_event.scorer;
+        celebration.goal = transforms
+            .get(goal_event.goal)
+            .map_or(Vec2::ZERO, |goal| goal.translation.truncate());
+        celebration.timer = Some(Timer::from_seconds(CELEBRATION_SECONDS, TimerMode::Once));
+    }
+}
+
+/// Runs on real time so the shake carries on through hit-stops.
+fn shake_camera(
+    time: Res<Time<Real>>,
+    mut trauma: ResMut<Trauma>,
+    mut rigs: Query<&mut CameraRig, With<MainCamera>>,
+) {
+    trauma.0 = (trauma.0 - TRAUMA_DECAY * time.delta_seconds()).max(0.);
+    let shake = trauma.0 * trauma.0;
+    let mut rng = rand::thread_rng();
+
+    for mut rig in &mut rigs {
+        let offset = Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0));
+        rig.shake_offset = offset * MAX_SHAKE_OFFSET * shake;
+        rig.shake_angle = rng.gen_range(-1.0..=1.0) * MAX_SHAKE_ANGLE * shake;
+    }
+}
+
+fn end_hit_stop(
+    real_time: Res<Time<Real>>,
+    mut hit_stop: ResMut<HitStop>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    let Some(timer) = hit_stop.0.as_mut() else {
+        return;
+    };
+
+    timer.tick(real_time.delta());
+
+    if timer.finished() {
+        time.set_relative_speed(1.);
+        hit_stop.0 = None;
+    }
+}
+
+/// Punches the camera in on the scorer and back out again.
+fn celebrate_goal(
+    time: Res<Time<Real>>,
+    mut celebration: ResMut<Celebration>,
+    players: Query<&Transform, With<Player>>,
+    mut rigs: Query<&mut CameraRig, With<MainCamera>>,
+) {
+    let mut punch = 0.;
+    if let Some(timer) = celebration.timer.as_mut() {
+        timer.tick(time.delta());
+        punch = (timer.fraction() * PI).sin();
+    }
+    if celebration.timer.as_ref().is_some_and(Timer::finished) {
+        celebration.timer = None;
+    }
+
+    let focus = celebration
+        .scorer
+        .and_then(|scorer| players.get(scorer).ok())
+        .map_or(celebration.goal, |scorer| scorer.translation.truncate());
+
+    for mut rig in &mut rigs {
+        rig.punch = punch;
+        rig.punch_focus = focus;
+    }
+}
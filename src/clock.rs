@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    mode::{pause_simulation, GameMode},
+    simulation::{GameplaySet, SIMULATION_HZ},
+};
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchClock>()
+            .add_event::<FullTime>()
+            .add_event::<NewMatch>()
+            .add_systems(
+                FixedUpdate,
+                run_clock
+                    .in_set(GameplaySet)
+                    .run_if(in_state(GameMode::Match)),
+            )
+            .add_systems(Update, pause_simulation.run_if(on_event::<FullTime>()));
+    }
+}
+
+/// How long the match has been going, counted in simulated time so it stops
+/// for replays and the editor. Matches with no length never end.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct MatchClock {
+    pub length: Option<Duration>,
+    pub elapsed: Duration,
+}
+
+impl MatchClock {
+    pub fn new(length: Option<Duration>) -> Self {
+        Self {
+            length,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        self.length
+            .map(|length| length.saturating_sub(self.elapsed))
+    }
+
+    pub fn is_full_time(&self) -> bool {
+        self.remaining() == Some(Duration::ZERO)
+    }
+
+    /// Fixed ticks played so far.
+    pub fn tick(&self) -> u32 {
+        (self.elapsed.as_secs_f64() * SIMULATION_HZ).round() as u32
+    }
+}
+
+/// Sent once, on the tick the clock runs out. The match is frozen there.
+#[derive(Event, Clone, Debug)]
+pub struct FullTime;
+
+/// Sent when a new match starts after full time, for anything kept over a
+/// match to be cleared.
+#[derive(Event, Clone, Debug)]
+pub struct NewMatch;
+
+pub(crate) fn run_clock(
+    time: Res<Time>,
+    mut clock: ResMut<MatchClock>,
+    mut full_time: EventWriter<FullTime>,
+) {
+    if clock.is_full_time() {
+        return;
+    }
+
+    clock.elapsed += time.delta();
+    if clock.is_full_time() {
+        full_time.send(FullTime);
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::ArenaFile,
+    clock::MatchClock,
+    constants::*,
+    match_log::MatchLog,
+    mode::GameMode,
+    obstacle::ObstacleLayout,
+    player::{TeamSize, Versus, MAX_PLAYERS_PER_TEAM, PLAYERS_PER_TEAM},
+    procedural::LayoutRng,
+};
+
+/// Read at launch if it exists, unless `--config` names another file.
+pub const CONFIG_PATH: &str = "soccer.ron";
+
+const USAGE: &str = "\
+Usage: bevy_soccer [OPTIONS]
+
+Options are read from soccer.ron if it exists, then overridden by these flags:
+  --config <PATH>            Read options from PATH instead of soccer.ron
+  --window <WIDTH>x<HEIGHT>  Window size, shrinking the pitch to fit if small
+  --fullscreen               Start fullscreen
+  --windowed                 Start in a window
+  --arena <ARENA>            classic, procedural or an arena file from the editor
+  --players <N>              Players per team, 1 to 4
+  --versus                   Put a second human on the other team
+  --match-length <SECONDS>   End the match after this long
+  --seed <N>                 Seed the procedural arenas
+  --mode <MODE>              Start in match, editor, viewer or shootout mode
+  --headless                 Play the match out with no window and print the score
+  --event-log <DIR>          Write each match's events to a .jsonl file in DIR
+  -h, --help                 Show this message";
+
+/// Which obstacles the first match is played around.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Arena {
+    #[default]
+    Classic,
+    Procedural,
+    /// An arena saved by the level editor.
+    File(PathBuf),
+}
+
+/// Everything that can be chosen at launch, from the config file and the
+/// command line. Fields left out of the file keep their defaults.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LaunchConfig {
+    /// Width and height in logical pixels.
+    pub window: (u32, u32),
+    pub fullscreen: bool,
+    pub arena: Arena,
+    pub players_per_team: usize,
+    /// A second human plays the other team. Otherwise only the first team
+    /// takes the pitch.
+    pub versus: bool,
+    /// In seconds. Matches go on forever without one.
+    pub match_length: Option<u32>,
+    /// Seeds the procedural arenas, the first one included, so a session can
+    /// be played again on the same pitches.
+    pub seed: Option<u64>,
+    pub mode: GameMode,
+    /// Run the match without a window, as fast as possible, until full time.
+    pub headless: bool,
+    /// Where to write a log of each match's events, if anywhere.
+    pub event_log: Option<PathBuf>,
+}
+
+impl Default for LaunchConfig {
+    fn default() -> Self {
+        Self {
+            window: (VIRTUAL_WIDTH as u32, VIRTUAL_HEIGHT as u32),
+            fullscreen: false,
+            arena: Arena::Classic,
+            players_per_team: PLAYERS_PER_TEAM,
+            versus: false,
+            match_length: None,
+            seed: None,
+            mode: GameMode::Match,
+            headless: false,
+            event_log: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// `--help` was passed. Shows the usage.
+    Help,
+    Read {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: ron::error::SpannedError,
+    },
+    UnknownFlag(String),
+    MissingValue(String),
+    BadValue {
+        flag: String,
+        value: String,
+        expected: &'static str,
+    },
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Help => write!(f, "{USAGE}"),
+            ConfigError::Read { path, error } => {
+                write!(f, "couldn't read {}: {error}", path.display())
+            }
+            ConfigError::Parse { path, error } => write!(f, "{}:{error}", path.display()),
+            ConfigError::UnknownFlag(flag) => write!(f, "unknown option {flag}"),
+            ConfigError::MissingValue(flag) => write!(f, "{flag} needs a value"),
+            ConfigError::BadValue {
+                flag,
+                value,
+                expected,
+            } => write!(f, "{flag} expects {expected}, got \"{value}\""),
+            ConfigError::Invalid(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl LaunchConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|error| ConfigError::Read {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        ron::from_str(&contents).map_err(|error| ConfigError::Parse {
+            path: path.to_path_buf(),
+            error,
+        })
+    }
+
+    /// Reads the config file and lays the flags in `args` over it. `args`
+    /// shouldn't include the program name.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, ConfigError> {
+        Self::from_args_with_default(args, CONFIG_PATH)
+    }
+
+    /// Like [`LaunchConfig::from_args`], with `default_path` read when
+    /// `--config` isn't given, if it exists.
+    pub fn from_args_with_default(
+        args: impl IntoIterator<Item = String>,
+        default_path: impl AsRef<Path>,
+    ) -> Result<Self, ConfigError> {
+        let args: Vec<String> = args.into_iter().collect();
+        let default_path = default_path.as_ref();
+
+        let path = args
+            .iter()
+            .position(|arg| arg == "--config")
+            .map(|index| {
+                args.get(index + 1)
+                    .ok_or_else(|| ConfigError::MissingValue("--config".into()))
+            })
+            .transpose()?;
+        let mut config = match path {
+            Some(path) => Self::load(path)?,
+            None if default_path.exists() => Self::load(default_path)?,
+            None => Self::default(),
+        };
+
+        config.apply_flags(&args)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply_flags(&mut self, args: &[String]) -> Result<(), ConfigError> {
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| ConfigError::MissingValue(flag.clone()))
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => return Err(ConfigError::Help),
+                // Already read.
+                "--config" => {
+                    value()?;
+                }
+                "--window" => {
+                    let size = value()?;
+                    self.window = size
+                        .split_once('x')
+                        .and_then(|(width, height)| {
+                            Some((width.parse().ok()?, height.parse().ok()?))
+                        })
+                        .ok_or_else(|| bad_value(flag, size, "a size like 1600x800"))?;
+                }
+                "--fullscreen" => self.fullscreen = true,
+                "--windowed" => self.fullscreen = false,
+                "--arena" => {
+                    self.arena = match value()?.as_str() {
+                        "classic" => Arena::Classic,
+                        "procedural" => Arena::Procedural,
+                        path => Arena::File(path.into()),
+                    }
+                }
+                "--players" => {
+                    self.players_per_team = parse(flag, value()?, "a number of players")?
+                }
+                "--versus" => self.versus = true,
+                "--match-length" => {
+                    self.match_length = Some(parse(flag, value()?, "a number of seconds")?)
+                }
+                "--seed" => self.seed = Some(parse(flag, value()?, "a whole number")?),
+                "--mode" => {
+                    self.mode = match value()?.as_str() {
+                        "match" => GameMode::Match,
+                        "editor" => GameMode::Editor,
+                        "viewer" => GameMode::Viewer,
+                        "shootout" => GameMode::Shootout,
+                        other => {
+                            return Err(bad_value(flag, other, "match, editor, viewer or shootout"))
+                        }
+                    }
+                }
+                "--headless" => self.headless = true,
+                "--event-log" => self.event_log = Some(value()?.into()),
+                other => return Err(ConfigError::UnknownFlag(other.into())),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Catches options that parse but can't be played.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: String| Err(ConfigError::Invalid(message));
+        let (width, height) = self.window;
+
+        // Smaller than the canvas is fine, it's shrunk to fit.
+        if width == 0 || height == 0 {
+            return invalid(format!(
+                "the window needs a width and a height, not {width}x{height}"
+            ));
+        }
+        if !(1..=MAX_PLAYERS_PER_TEAM).contains(&self.players_per_team) {
+            return invalid(format!(
+                "teams need between 1 and {MAX_PLAYERS_PER_TEAM} players, not {}",
+                self.players_per_team
+            ));
+        }
+        if let Arena::File(path) = &self.arena {
+            if let Err(error) = ArenaFile::load(path) {
+                return invalid(format!("can't read the arena {}: {error}", path.display()));
+            }
+        }
+        if self.match_length == Some(0) {
+            return invalid("the match length must be at least a second".into());
+        }
+        if self.mode == GameMode::Replay {
+            return invalid("replays only follow goals, so the game can't start in one".into());
+        }
+        if self.headless && self.mode != GameMode::Match {
+            return invalid("headless runs can only play matches".into());
+        }
+        if self.headless && self.match_length.is_none() {
+            return invalid("headless runs need a match length, or they would never finish".into());
+        }
+
+        Ok(())
+    }
+
+    /// Sets up what the game plugins read when they start. Call it before
+    /// adding them, since they only fill in what isn't there yet.
+    pub fn apply(&self, app: &mut App) {
+        let mut rng = self.seed.map_or_else(LayoutRng::default, LayoutRng::seeded);
+        let layout = match &self.arena {
+            Arena::Classic => ObstacleLayout::Classic,
+            Arena::Procedural => ObstacleLayout::Procedural {
+                seed: self.seed.unwrap_or_else(|| rng.next_seed()),
+            },
+            Arena::File(path) => ObstacleLayout::File(path.clone()),
+        };
+        let match_length = self
+            .match_length
+            .map(|seconds| Duration::from_secs(seconds.into()));
+
+        app.insert_resource(TeamSize(self.players_per_team))
+            .insert_resource(Versus(self.versus))
+            .insert_resource(layout)
+            .insert_resource(rng)
+            .insert_resource(MatchClock::new(match_length))
+            .insert_resource(MatchLog::new(self.event_log.clone()))
+            .insert_state(self.mode);
+    }
+}
+
+fn parse<T: FromStr>(flag: &str, value: &str, expected: &'static str) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| bad_value(flag, value, expected))
+}
+
+fn bad_value(flag: &str, value: &str, expected: &'static str) -> ConfigError {
+    ConfigError::BadValue {
+        flag: flag.into(),
+        value: value.into(),
+        expected,
+    }
+}
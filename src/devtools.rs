@@ -0,0 +1,208 @@
+use bevy::{
+    diagnostic::{
+        DiagnosticPath, DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin,
+    },
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+};
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    camera::{apply_camera_rig, Canvas, MainCamera, UI_LAYER},
+    utils::display_events,
+};
+
+/// Debugging aids, each behind its own hotkey. Only built with the
+/// `devtools` feature.
+pub struct DevToolsPlugin;
+
+impl Plugin for DevToolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DevTools>()
+            .init_resource::<FreeCamera>()
+            .add_plugins((
+                RapierDebugRenderPlugin::default().disabled(),
+                WorldInspectorPlugin::new().run_if(|tools: Res<DevTools>| tools.inspector),
+                FrameTimeDiagnosticsPlugin,
+                EntityCountDiagnosticsPlugin,
+            ))
+            .add_systems(Startup, spawn_stats_overlay)
+            .add_systems(
+                Update,
+                (
+                    toggle_dev_tools,
+                    show_physics.run_if(resource_changed::<DevTools>),
+                    display_events.run_if(|tools: Res<DevTools>| tools.collisions),
+                    update_stats_overlay,
+                    release_free_camera.run_if(|tools: Res<DevTools>| !tools.free_camera),
+                    (grab_free_camera, move_free_camera)
+                        .chain()
+                        .after(apply_camera_rig)
+                        .run_if(|tools: Res<DevTools>| tools.free_camera),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Which dev tools are switched on.
+#[derive(Resource, Default)]
+pub struct DevTools {
+    /// `F2`: frame rate and entity count.
+    pub stats: bool,
+    /// `F4`: collider outlines.
+    pub physics: bool,
+    /// `F8`: logs every collision as it starts and stops.
+    pub collisions: bool,
+    /// `F10`: the egui world inspector.
+    pub inspector: bool,
+    /// `F12`: drag with the middle mouse button to pan and scroll to zoom,
+    /// leaving the ball behind.
+    pub free_camera: bool,
+}
+
+/// Where the free camera is looking. It starts wherever the main camera was
+/// when it was switched on.
+#[derive(Resource, Default)]
+struct FreeCamera {
+    focus: Option<Vec2>,
+    zoom: f32,
+}
+
+const MIN_FREE_ZOOM: f32 = 0.25;
+const MAX_FREE_ZOOM: f32 = 4.;
+/// How much one notch of the scroll wheel zooms.
+const ZOOM_STEP: f32 = 0.9;
+
+#[derive(Component)]
+struct StatsOverlay;
+
+fn toggle_dev_tools(keys: Res<ButtonInput<KeyCode>>, mut tools: ResMut<DevTools>) {
+    let tools = &mut *tools;
+    let toggles = [
+        (KeyCode::F2, &mut tools.stats),
+        (KeyCode::F4, &mut tools.physics),
+        (KeyCode::F8, &mut tools.collisions),
+        (KeyCode::F10, &mut tools.inspector),
+        (KeyCode::F12, &mut tools.free_camera),
+    ];
+
+    for (key, enabled) in toggles {
+        if keys.just_pressed(key) {
+            *enabled = !*enabled;
+        }
+    }
+}
+
+fn show_physics(tools: Res<DevTools>, mut debug_render: ResMut<DebugRenderContext>) {
+    debug_render.enabled = tools.physics;
+}
+
+/// Starts from wherever the main camera is next time it's switched on.
+fn release_free_camera(mut free_camera: ResMut<FreeCamera>) {
+    free_camera.focus = None;
+}
+
+/// Picks up the main camera's view the first frame the free camera is on.
+fn grab_free_camera(
+    mut free_camera: ResMut<FreeCamera>,
+    cameras: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+) {
+    if free_camera.focus.is_some() {
+        return;
+    }
+    let Ok((transform, projection)) = cameras.get_single() else {
+        return;
+    };
+
+    free_camera.focus = Some(transform.translation.truncate());
+    free_camera.zoom = projection.scale;
+}
+
+/// Runs after the rig has placed the camera, so it wins over following the
+/// ball and the camera effects.
+fn move_free_camera(
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    canvas: Res<Canvas>,
+    mut free_camera: ResMut<FreeCamera>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let FreeCamera { focus, zoom } = &mut *free_camera;
+    let Some(focus) = focus else {
+        return;
+    };
+
+    for event in wheel.read() {
+        *zoom = (*zoom * ZOOM_STEP.powf(event.y)).clamp(MIN_FREE_ZOOM, MAX_FREE_ZOOM);
+    }
+
+    let dragged: Vec2 = motion.read().map(|event| event.delta).sum();
+    if mouse.pressed(MouseButton::Middle) {
+        // Window pixels to world units, with y pointing up.
+        *focus -= Vec2::new(dragged.x, -dragged.y) / canvas.scale * *zoom;
+    }
+
+    for (mut transform, mut projection) in &mut cameras {
+        transform.translation.x = focus.x;
+        transform.translation.y = focus.y;
+        transform.rotation = Quat::IDENTITY;
+        projection.scale = *zoom;
+    }
+}
+
+fn spawn_stats_overlay(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/More 15 Basic.ttf");
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                color: Color::YELLOW,
+                font_size: 16.0,
+                font,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(4.),
+            left: Val::Px(4.),
+            ..default()
+        }),
+        StatsOverlay,
+        UI_LAYER,
+    ));
+}
+
+fn update_stats_overlay(
+    tools: Res<DevTools>,
+    diagnostics: Res<DiagnosticsStore>,
+    mut query: Query<(&mut Text, &mut Visibility), With<StatsOverlay>>,
+) {
+    let value = |path: &DiagnosticPath| {
+        diagnostics
+            .get(path)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or(0.)
+    };
+    let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+
+    for (mut text, mut visibility) in &mut query {
+        *visibility = if tools.stats {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        text.sections[0].value = format!(
+            "FPS {:.0}  entities {:.0}\nF4 physics {}  F8 collisions {}  F10 inspector {}  F12 free camera {}",
+            value(&FrameTimeDiagnosticsPlugin::FPS),
+            value(&EntityCountDiagnosticsPlugin::ENTITY_COUNT),
+            on_off(tools.physics),
+            on_off(tools.collisions),
+            on_off(tools.inspector),
+            on_off(tools.free_camera),
+        );
+    }
+}
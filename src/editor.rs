@@ -0,0 +1,500 @@
+use std::{borrow::Cow, f32::consts::PI};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    arena::{ArenaFile, Goal, GoalPlacement, GOAL_HEIGHT, GOAL_WIDTH},
+    camera::{Canvas, MainCamera, UI_LAYER},
+    constants::*,
+    mode::{pause_simulation, resume_unless_full_time, GameMode},
+    obstacle::{
+        spawn_obstacle, Obstacle, ObstacleAnchor, ObstacleLayout, ObstacleVariant, OBSTACLE_SIZE,
+        PATROL_VERTICAL,
+    },
+};
+
+pub struct LevelEditorPlugin;
+
+impl Plugin for LevelEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditorSettings>()
+            .init_resource::<EditorSelection>()
+            .init_resource::<EditorCursor>()
+            .add_systems(Update, toggle_editor)
+            .add_systems(
+                OnEnter(GameMode::Editor),
+                (pause_simulation, spawn_editor_help),
+            )
+            .add_systems(
+                OnExit(GameMode::Editor),
+                (
+                    resume_unless_full_time,
+                    clear_selection,
+                    despawn_editor_help,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    track_cursor,
+                    select_or_place,
+                    drag_selected,
+                    rotate_selected,
+                    configure_selected,
+                    delete_selected,
+                    toggle_snapping,
+                    save_arena,
+                    load_arena,
+                    draw_selection,
+                    update_editor_help,
+                )
+                    .chain()
+                    .run_if(in_state(GameMode::Editor)),
+            );
+    }
+}
+
+/// Where the editor saves to and loads from.
+pub const ARENA_PATH: &str = "assets/arenas/custom.ron";
+
+const SNAP_SIZE: f32 = 20.;
+const ROTATION_STEP: f32 = PI / 12.;
+
+const PALETTE_KEYS: [KeyCode; 7] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+];
+const PALETTE_NAMES: [&str; 7] = [
+    "static",
+    "spinning",
+    "expanding",
+    "patrol",
+    "bumper",
+    "teleporter",
+    "breakable",
+];
+
+#[derive(Resource)]
+struct EditorSettings {
+    snapping: bool,
+    palette: usize,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        Self {
+            snapping: true,
+            palette: 0,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct EditorSelection {
+    entity: Option<Entity>,
+    dragging: bool,
+}
+
+/// The cursor in world coordinates, if it is over the window.
+#[derive(Resource, Default)]
+struct EditorCursor(Option<Vec2>);
+
+#[derive(Component)]
+struct EditorHelp;
+
+fn palette_variant(index: usize) -> ObstacleVariant {
+    match index {
+        1 => ObstacleVariant::Spinning { clockwise: true },
+        2 => ObstacleVariant::Expanding {
+            speed: 0.5,
+            max_scale: 2.,
+            min_scale: 1.,
+            expanding: true,
+            horizontal: true,
+            scale: 1.,
+        },
+        3 => ObstacleVariant::Patrol {
+            waypoints: Cow::Borrowed(&PATROL_VERTICAL),
+            speed: 60.,
+            leg: 0,
+            progress: 0.,
+        },
+        4 => ObstacleVariant::Bumper { strength: 400. },
+        5 => ObstacleVariant::Teleporter { pair: 0 },
+        6 => ObstacleVariant::Breakable { hits: 3 },
+        _ => ObstacleVariant::Static,
+    }
+}
+
+/// Nudges the setting that matters most for each kind of obstacle.
+fn adjust(variant: &mut ObstacleVariant, step: f32) {
+    match variant {
+        ObstacleVariant::Static => {}
+        ObstacleVariant::Spinning { clockwise } => *clockwise = !*clockwise,
+        ObstacleVariant::Expanding {
+            max_scale,
+            min_scale,
+            ..
+        } => *max_scale = (*max_scale + step * 0.25).max(*min_scale + 0.25),
+        ObstacleVariant::Patrol { speed, .. } => *speed = (*speed + step * 10.).max(10.),
+        ObstacleVariant::Bumper { strength } => *strength = (*strength + step * 50.).max(50.),
+        ObstacleVariant::Teleporter { pair } => *pair = pair.saturating_add_signed(step as i8),
+        ObstacleVariant::Breakable { hits } => {
+            *hits = hits.saturating_add_signed(step as i32).max(1)
+        }
+    }
+}
+
+fn snap(position: Vec2, snapping: bool) -> Vec2 {
+    if snapping {
+        (position / SNAP_SIZE).round() * SNAP_SIZE
+    } else {
+        position
+    }
+}
+
+fn rotation_z(transform: &Transform) -> f32 {
+    transform.rotation.to_euler(EulerRot::XYZ).2
+}
+
+fn contains(transform: &Transform, size: Vec2, point: Vec2) -> bool {
+    let local = transform.rotation.inverse()
+        * (point.extend(transform.translation.z) - transform.translation);
+    local.x.abs() <= size.x / 2. && local.y.abs() <= size.y / 2.
+}
+
+fn on_pitch(point: Vec2) -> bool {
+    point.x.abs() <= GROUND_WIDTH / 2. && (point.y - GROUND_MIDDLE).abs() <= GROUND_HEIGHT / 2.
+}
+
+fn toggle_editor(
+    keys: Res<ButtonInput<KeyCode>>,
+    mode: Res<State<GameMode>>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+) {
+    if keys.just_pressed(KeyCode::F1) {
+        next_mode.set(match mode.get() {
+            GameMode::Match => GameMode::Editor,
+            GameMode::Editor => GameMode::Match,
+            GameMode::Replay | GameMode::Viewer | GameMode::Shootout => return,
+        });
+    }
+}
+
+fn clear_selection(mut selection: ResMut<EditorSelection>) {
+    *selection = EditorSelection::default();
+}
+
+fn track_cursor(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    canvas: Res<Canvas>,
+    mut cursor: ResMut<EditorCursor>,
+) {
+    cursor.0 = windows
+        .get_single()
+        .ok()
+        .and_then(|window| canvas.cursor_position(window))
+        .zip(cameras.get_single().ok())
+        .and_then(|(position, (camera, camera_transform))| {
+            camera.viewport_to_world_2d(camera_transform, position)
+        });
+}
+
+/// Clicking an obstacle or goal picks it up; clicking empty pitch drops a new
+/// obstacle from the palette there.
+fn select_or_place(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    cursor: Res<EditorCursor>,
+    settings: Res<EditorSettings>,
+    mut selection: ResMut<EditorSelection>,
+    obstacles: Query<(Entity, &Transform, &Sprite), With<ObstacleVariant>>,
+    goals: Query<(Entity, &Transform), With<Goal>>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(cursor) = cursor.0 else {
+        return;
+    };
+
+    let obstacle_hit = obstacles.iter().find_map(|(entity, transform, sprite)| {
+        let size = sprite.custom_size.unwrap_or(Vec2::splat(OBSTACLE_SIZE));
+        contains(transform, size * transform.scale.truncate(), cursor).then_some(entity)
+    });
+    let goal_hit = goals.iter().find_map(|(entity, transform)| {
+        contains(transform, Vec2::new(GOAL_WIDTH, GOAL_HEIGHT), cursor).then_some(entity)
+    });
+
+    if let Some(entity) = obstacle_hit.or(goal_hit) {
+        selection.entity = Some(entity);
+    } else if on_pitch(cursor) {
+        let obstacle = Obstacle {
+            translation: snap(cursor, settings.snapping).extend(4.),
+            rotation: 0.,
+            variant: palette_variant(settings.palette),
+        };
+        selection.entity = Some(spawn_obstacle(&mut commands, obstacle));
+    } else {
+        selection.entity = None;
+    }
+
+    selection.dragging = selection.entity.is_some();
+}
+
+fn drag_selected(
+    mouse: Res<ButtonInput<MouseButton>>,
+    cursor: Res<EditorCursor>,
+    settings: Res<EditorSettings>,
+    mut selection: ResMut<EditorSelection>,
+    mut query: Query<(&mut Transform, Option<&mut ObstacleAnchor>)>,
+) {
+    if !mouse.pressed(MouseButton::Left) {
+        selection.dragging = false;
+        return;
+    }
+
+    let (true, Some(entity), Some(cursor)) = (selection.dragging, selection.entity, cursor.0)
+    else {
+        return;
+    };
+
+    if let Ok((mut transform, anchor)) = query.get_mut(entity) {
+        let position = snap(cursor, settings.snapping);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+
+        // Patrolling obstacles are positioned relative to their anchor.
+        if let Some(mut anchor) = anchor {
+            anchor.0 = transform.translation;
+        }
+    }
+}
+
+/// `Q` and `E` turn the selection, in fixed steps while snapping is on.
+fn rotate_selected(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<EditorSettings>,
+    selection: Res<EditorSelection>,
+    time: Res<Time<Real>>,
+    mut query: Query<&mut Transform>,
+) {
+    let Some(entity) = selection.entity else {
+        return;
+    };
+    let Ok(mut transform) = query.get_mut(entity) else {
+        return;
+    };
+
+    if settings.snapping {
+        if keys.just_pressed(KeyCode::KeyQ) {
+            transform.rotate_z(ROTATION_STEP);
+        }
+        if keys.just_pressed(KeyCode::KeyE) {
+            transform.rotate_z(-ROTATION_STEP);
+        }
+    } else {
+        if keys.pressed(KeyCode::KeyQ) {
+            transform.rotate_z(time.delta_seconds() * PI);
+        }
+        if keys.pressed(KeyCode::KeyE) {
+            transform.rotate_z(-time.delta_seconds() * PI);
+        }
+    }
+}
+
+/// Number keys pick the palette entry (and swap the selected obstacle to it),
+/// `[` and `]` tweak the selected obstacle.
+fn configure_selected(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<EditorSettings>,
+    mut selection: ResMut<EditorSelection>,
+    mut obstacles: Query<(&mut ObstacleVariant, &ObstacleAnchor, &Transform)>,
+) {
+    for (index, key) in PALETTE_KEYS.into_iter().enumerate() {
+        if !keys.just_pressed(key) {
+            continue;
+        }
+        settings.palette = index;
+
+        // The body type, sensor and colour all depend on the variant, so the
+        // obstacle is rebuilt rather than patched.
+        if let Some(entity) = selection.entity {
+            if let Ok((_, anchor, transform)) = obstacles.get(entity) {
+                let obstacle = Obstacle {
+                    translation: anchor.0,
+                    rotation: rotation_z(transform),
+                    variant: palette_variant(index),
+                };
+                commands.entity(entity).despawn();
+                selection.entity = Some(spawn_obstacle(&mut commands, obstacle));
+            }
+        }
+    }
+
+    let step = if keys.just_pressed(KeyCode::BracketRight) {
+        1.
+    } else if keys.just_pressed(KeyCode::BracketLeft) {
+        -1.
+    } else {
+        return;
+    };
+
+    if let Some(entity) = selection.entity {
+        if let Ok((mut variant, _, _)) = obstacles.get_mut(entity) {
+            adjust(variant.as_mut(), step);
+        }
+    }
+}
+
+/// Goals can be moved but not removed, or the match could never be won.
+fn delete_selected(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut selection: ResMut<EditorSelection>,
+    obstacles: Query<(), With<ObstacleVariant>>,
+) {
+    if !keys.just_pressed(KeyCode::Delete) {
+        return;
+    }
+
+    if let Some(entity) = selection.entity {
+        if obstacles.contains(entity) {
+            commands.entity(entity).despawn();
+            *selection = EditorSelection::default();
+        }
+    }
+}
+
+fn toggle_snapping(keys: Res<ButtonInput<KeyCode>>, mut settings: ResMut<EditorSettings>) {
+    if keys.just_pressed(KeyCode::KeyG) {
+        settings.snapping = !settings.snapping;
+    }
+}
+
+fn save_arena(
+    keys: Res<ButtonInput<KeyCode>>,
+    obstacles: Query<(&ObstacleVariant, &ObstacleAnchor, &Transform)>,
+    goals: Query<&Transform, With<Goal>>,
+) {
+    if !keys.just_pressed(KeyCode::F5) {
+        return;
+    }
+
+    let arena = ArenaFile {
+        goals: goals
+            .iter()
+            .map(|transform| GoalPlacement {
+                translation: transform.translation,
+                rotation: rotation_z(transform),
+            })
+            .collect(),
+        obstacles: obstacles
+            .iter()
+            .map(|(variant, anchor, transform)| Obstacle {
+                translation: anchor.0,
+                rotation: rotation_z(transform),
+                variant: variant.clone(),
+            })
+            .collect(),
+    };
+
+    match arena.save(ARENA_PATH) {
+        Ok(()) => info!("Saved arena to {}", ARENA_PATH),
+        Err(error) => error!("Failed to save arena to {}: {}", ARENA_PATH, error),
+    }
+}
+
+/// Switches to the saved arena, which is then built like any other layout,
+/// so results and recordings know which arena they were played on.
+fn load_arena(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut selection: ResMut<EditorSelection>,
+    mut layout: ResMut<ObstacleLayout>,
+) {
+    if !keys.just_pressed(KeyCode::F9) {
+        return;
+    }
+
+    // Checked first so a bad file leaves the arena as it is.
+    if let Err(error) = ArenaFile::load(ARENA_PATH) {
+        error!("Failed to load arena from {}: {}", ARENA_PATH, error);
+        return;
+    }
+
+    *selection = EditorSelection::default();
+    *layout = ObstacleLayout::File(ARENA_PATH.into());
+    info!("Loaded arena from {}", ARENA_PATH);
+}
+
+fn draw_selection(
+    mut gizmos: Gizmos,
+    selection: Res<EditorSelection>,
+    query: Query<(&Transform, Option<&Sprite>)>,
+) {
+    let Some(entity) = selection.entity else {
+        return;
+    };
+
+    if let Ok((transform, sprite)) = query.get(entity) {
+        let size = sprite
+            .and_then(|sprite| sprite.custom_size)
+            .unwrap_or(Vec2::new(GOAL_WIDTH, GOAL_HEIGHT))
+            * transform.scale.truncate();
+        gizmos.rect_2d(
+            transform.translation.truncate(),
+            rotation_z(transform),
+            size + 4.,
+            Color::CYAN,
+        );
+    }
+}
+
+fn spawn_editor_help(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/More 15 Basic.ttf");
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                color: Color::WHITE,
+                font_size: 16.0,
+                font,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(4.),
+            left: Val::Px(4.),
+            ..default()
+        }),
+        EditorHelp,
+        UI_LAYER,
+    ));
+}
+
+fn update_editor_help(
+    settings: Res<EditorSettings>,
+    mut query: Query<&mut Text, With<EditorHelp>>,
+) {
+    for mut text in &mut query {
+        text.sections[0].value = format!(
+            "EDITOR  1-7 {}  [ ] tweak  Q/E rotate  G snap {}\nDEL remove  F5 save  F9 load  F1 play",
+            PALETTE_NAMES[settings.palette],
+            if settings.snapping { "on" } else { "off" },
+        );
+    }
+}
+
+fn despawn_editor_help(mut commands: Commands, query: Query<Entity, With<EditorHelp>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
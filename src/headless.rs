@@ -0,0 +1,260 @@
+use std::{
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use bevy::{
+    app::PluginGroupBuilder,
+    core::{FrameCountPlugin, TaskPoolPlugin, TypeRegistrationPlugin},
+    ecs::system::CommandQueue,
+    hierarchy::HierarchyPlugin,
+    input::InputPlugin,
+    prelude::*,
+    scene::ScenePlugin,
+    text::Font,
+    time::{TimePlugin, TimeUpdateStrategy},
+    transform::TransformPlugin,
+};
+use bevy_pkv::PkvStore;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    arena::ArenaPlugin,
+    ball::{Ball, BallPlugin},
+    clock::{ClockPlugin, MatchClock},
+    config::LaunchConfig,
+    constants::*,
+    heatmap::HeatmapPlugin,
+    leaderboard::LeaderboardPlugin,
+    match_log::MatchLogPlugin,
+    mode::{GameMode, ModePlugin},
+    obstacle::{spawn_obstacle, Obstacle, ObstaclePlugin, ObstacleVariant},
+    player::PlayerPlugin,
+    profile::ProfilePlugin,
+    recording::{MatchRecorder, MatchViewer},
+    save::SavePlugin,
+    shootout::ShootoutPlugin,
+    simulation::{SimulationPlugin, SIMULATION_HZ},
+    stats::StatsPlugin,
+    suspend::SuspendPlugin,
+};
+
+/// The gameplay plugins on top of the bare minimum Bevy needs to run them,
+/// with no window, renderer, audio or HUD.
+pub struct HeadlessPlugins;
+
+impl PluginGroup for HeadlessPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(TaskPoolPlugin::default())
+            .add(TypeRegistrationPlugin)
+            .add(FrameCountPlugin)
+            .add(TimePlugin)
+            .add(TransformPlugin)
+            .add(HierarchyPlugin)
+            .add(InputPlugin)
+            .add(AssetPlugin::default())
+            .add(ScenePlugin)
+            .add(HeadlessAssetsPlugin)
+            .add(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.).in_fixed_schedule())
+            .add(ModePlugin)
+            .add(SavePlugin)
+            .add(SimulationPlugin)
+            .add(ArenaPlugin)
+            .add(BallPlugin)
+            .add(ClockPlugin)
+            .add(MatchLogPlugin)
+            .add(ObstaclePlugin)
+            .add(PlayerPlugin)
+            .add(ProfilePlugin)
+            .add(StatsPlugin)
+            .add(HeatmapPlugin)
+            .add(LeaderboardPlugin)
+            .add(SuspendPlugin)
+            .add(ShootoutPlugin)
+    }
+}
+
+/// Registers the asset types the gameplay plugins hand out handles for,
+/// which the render and text plugins would normally do.
+struct HeadlessAssetsPlugin;
+
+impl Plugin for HeadlessAssetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Image>()
+            .init_asset::<Mesh>()
+            .init_asset::<ColorMaterial>()
+            .init_asset::<TextureAtlasLayout>()
+            .init_asset::<Font>();
+    }
+}
+
+/// A headless game that advances exactly one simulation tick per `step`.
+pub struct HeadlessApp {
+    pub app: App,
+}
+
+/// Every event of type `E` sent since it was added with
+/// [`HeadlessApp::record_events`].
+#[derive(Resource)]
+pub struct EventLog<E: Event>(pub Vec<E>);
+
+impl HeadlessApp {
+    pub fn new() -> Self {
+        Self::with_config(&LaunchConfig::default())
+    }
+
+    /// A game set up from the launch options. The window options are
+    /// ignored.
+    pub fn with_config(config: &LaunchConfig) -> Self {
+        Self::with_store(config, PkvStore::new_in_dir(scratch_dir()))
+    }
+
+    /// Like [`HeadlessApp::with_config`], loading its save from `pkv`.
+    pub fn with_store(config: &LaunchConfig, pkv: PkvStore) -> Self {
+        let mut app = App::new();
+        config.apply(&mut app);
+        app.add_plugins(HeadlessPlugins)
+            .insert_resource(pkv)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                1. / SIMULATION_HZ,
+            )));
+
+        // The first update runs the startup systems but doesn't move the
+        // clock, so take one tick more for the ball to be spawned.
+        app.update();
+        let mut headless = Self { app };
+        headless.step(1);
+        headless
+    }
+
+    /// A match that reaches full time after `seconds`, on an empty pitch.
+    pub fn timed_match(seconds: u32) -> Self {
+        let mut game = Self::with_config(&LaunchConfig {
+            match_length: Some(seconds),
+            ..LaunchConfig::default()
+        });
+        game.clear_obstacles();
+        game
+    }
+
+    pub fn step(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            self.app.update();
+        }
+    }
+
+    pub fn step_seconds(&mut self, seconds: f32) {
+        self.step((seconds * SIMULATION_HZ as f32).round() as usize);
+    }
+
+    /// Steps until the match clock runs out.
+    ///
+    /// # Panics
+    ///
+    /// If the match has no length.
+    pub fn play_to_full_time(&mut self) {
+        assert!(
+            self.world().resource::<MatchClock>().length.is_some(),
+            "the match has no length, so it never reaches full time"
+        );
+        while !self.world().resource::<MatchClock>().is_full_time() {
+            self.step(1);
+        }
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    /// Starts collecting `E` into an [`EventLog`].
+    pub fn record_events<E: Event + Clone>(&mut self) -> &mut Self {
+        self.app
+            .insert_resource(EventLog::<E>(Vec::new()))
+            .add_systems(Last, log_events::<E>);
+        self
+    }
+
+    pub fn events<E: Event + Clone>(&self) -> &[E] {
+        &self.app.world.resource::<EventLog<E>>().0
+    }
+
+    pub fn ball(&mut self) -> Entity {
+        self.world()
+            .query_filtered::<Entity, With<Ball>>()
+            .single(&self.app.world)
+    }
+
+    /// Puts the ball at `translation`, moving at `linvel`.
+    pub fn kick_ball(&mut self, translation: Vec2, linvel: Vec2) {
+        let ball = self.ball();
+        let mut entity = self.world().entity_mut(ball);
+        entity.get_mut::<Transform>().unwrap().translation = translation.extend(1.);
+        entity.get_mut::<Velocity>().unwrap().linvel = linvel;
+    }
+
+    /// Shoots at the goal `team` attacks from close in, and waits long
+    /// enough for it to go in. Nothing should be in the way.
+    pub fn score_for(&mut self, team: usize) {
+        let side = if team == 0 { 1. } else { -1. };
+        self.kick_ball(
+            Vec2::new(300. * side, GROUND_MIDDLE),
+            Vec2::new(400. * side, 0.),
+        );
+        self.step_seconds(2.);
+    }
+
+    /// Opens the match recorded so far in the viewer, as `F6` then `F7`
+    /// would without going through the file. Needs the `RecordingPlugin`.
+    pub fn watch_recording(&mut self) {
+        let recording = self.world().resource::<MatchRecorder>().recording.clone();
+        self.world().resource_mut::<MatchViewer>().recording = recording;
+        self.world()
+            .resource_mut::<NextState<GameMode>>()
+            .set(GameMode::Viewer);
+        self.step(1);
+    }
+
+    /// Removes every obstacle, leaving an empty pitch.
+    pub fn clear_obstacles(&mut self) {
+        let obstacles: Vec<Entity> = self
+            .world()
+            .query_filtered::<Entity, With<ObstacleVariant>>()
+            .iter(&self.app.world)
+            .collect();
+        for obstacle in obstacles {
+            self.world().entity_mut(obstacle).despawn_recursive();
+        }
+    }
+
+    pub fn spawn_obstacle(&mut self, obstacle: Obstacle) -> Entity {
+        let mut queue = CommandQueue::default();
+        let entity = spawn_obstacle(&mut Commands::new(&mut queue, &self.app.world), obstacle);
+        queue.apply(self.world());
+        entity
+    }
+}
+
+impl Default for HeadlessApp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn log_events<E: Event + Clone>(mut events: EventReader<E>, mut log: ResMut<EventLog<E>>) {
+    log.0.extend(events.read().cloned());
+}
+
+/// Each app gets its own store so tests running side by side don't share a
+/// score.
+fn scratch_dir() -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    std::env::temp_dir().join(format!(
+        "bevy_soccer-{}-{}",
+        process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ))
+}
@@ -0,0 +1,329 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+use crate::{
+    ball::Ball,
+    camera::UI_LAYER,
+    clock::NewMatch,
+    constants::*,
+    mode::GameMode,
+    player::{Player, PlayerNumber, Team, TEAM_NAMES},
+    simulation::GameplaySet,
+    stats::FullTimeScreen,
+};
+
+pub struct HeatmapPlugin;
+
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Heatmaps>()
+            .init_resource::<HeatmapView>()
+            .add_systems(
+                FixedUpdate,
+                sample_positions
+                    .in_set(GameplaySet)
+                    .run_if(in_state(GameMode::Match)),
+            )
+            .add_systems(
+                Update,
+                (
+                    (cycle_heatmap, export_heatmaps).run_if(in_state(GameMode::Match)),
+                    reset_heatmaps.run_if(on_event::<NewMatch>()),
+                ),
+            );
+    }
+}
+
+/// Exported heatmaps go in a folder per match in here.
+pub const HEATMAP_DIR: &str = "heatmaps";
+
+/// Ticks between samples, so a second of play adds 16 to each heatmap.
+const SAMPLE_TICKS: u32 = 4;
+
+/// Each cell covers a square this many pixels across.
+const CELL_SIZE: f32 = 10.;
+const COLUMNS: usize = (GROUND_WIDTH / CELL_SIZE) as usize;
+const ROWS: usize = (GROUND_HEIGHT / CELL_SIZE) as usize;
+
+/// Drawn over the players and obstacles, under the HUD.
+const OVERLAY_Z: f32 = 50.;
+
+/// How often something was seen in each part of the ground, in cells of
+/// `CELL_SIZE` pixels counted from the top left.
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    counts: Vec<u32>,
+}
+
+impl Default for Heatmap {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; COLUMNS * ROWS],
+        }
+    }
+}
+
+impl Heatmap {
+    /// Positions off the ground are counted in the nearest cell.
+    pub fn add(&mut self, position: Vec2) {
+        let index = Self::index(position);
+        self.counts[index] += 1;
+    }
+
+    /// How many samples fell in the cell around `position`.
+    pub fn count(&self, position: Vec2) -> u32 {
+        self.counts[Self::index(position)]
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.counts.iter().sum()
+    }
+
+    fn index(position: Vec2) -> usize {
+        let left = -GROUND_WIDTH / 2.;
+        let top = GROUND_MIDDLE + GROUND_HEIGHT / 2.;
+        let column = ((position.x - left) / CELL_SIZE).floor() as isize;
+        let row = ((top - position.y) / CELL_SIZE).floor() as isize;
+        let column = column.clamp(0, COLUMNS as isize - 1) as usize;
+        let row = row.clamp(0, ROWS as isize - 1) as usize;
+        row * COLUMNS + column
+    }
+
+    /// One RGBA pixel per cell, from clear where nothing went through blue
+    /// and yellow to red at the busiest cell.
+    fn pixels(&self) -> Vec<u8> {
+        let max = self.counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+        self.counts
+            .iter()
+            .flat_map(|&count| {
+                // The square root keeps the quieter cells from vanishing next
+                // to the kickoff spot.
+                let heat = (count as f32 / max).sqrt();
+                let color = if heat < 0.5 {
+                    Color::BLUE
+                        .rgba_to_vec4()
+                        .lerp(Color::YELLOW.rgba_to_vec4(), heat * 2.)
+                } else {
+                    Color::YELLOW
+                        .rgba_to_vec4()
+                        .lerp(Color::RED.rgba_to_vec4(), heat * 2. - 1.)
+                };
+                let alpha = if count == 0 { 0. } else { 0.3 + heat * 0.5 };
+                [color.x, color.y, color.z, alpha].map(|channel| (channel * 255.) as u8)
+            })
+            .collect()
+    }
+
+    /// A texture for drawing over the ground, one texel per cell.
+    pub fn to_image(&self) -> Image {
+        Image::new(
+            Extent3d {
+                width: COLUMNS as u32,
+                height: ROWS as u32,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            self.pixels(),
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+    }
+
+    /// Writes the heatmap at the size of the ground, so it lines up with a
+    /// screenshot of the arena.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let cell = CELL_SIZE as u32;
+        let pixels = self.pixels();
+        let png = image::RgbaImage::from_fn(COLUMNS as u32 * cell, ROWS as u32 * cell, |x, y| {
+            let index = ((y / cell) as usize * COLUMNS + (x / cell) as usize) * 4;
+            image::Rgba([
+                pixels[index],
+                pixels[index + 1],
+                pixels[index + 2],
+                pixels[index + 3],
+            ])
+        });
+        png.save(path)?;
+        Ok(())
+    }
+}
+
+/// Where the ball and each player spent the match, keyed by `(team,
+/// number)`.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Heatmaps {
+    pub ball: Heatmap,
+    pub players: BTreeMap<(usize, usize), Heatmap>,
+}
+
+impl Heatmaps {
+    /// Every heatmap, with a name fit for a file or a caption.
+    pub fn named(&self) -> Vec<(String, &Heatmap)> {
+        let mut heatmaps = vec![("ball".to_string(), &self.ball)];
+        for (&(team, number), heatmap) in &self.players {
+            let name = format!("{}-{}", TEAM_NAMES[team].to_lowercase(), number + 1);
+            heatmaps.push((name, heatmap));
+        }
+        heatmaps
+    }
+
+    /// Saves each heatmap as `<name>.png` in `dir`, and returns the files
+    /// written.
+    pub fn export(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        self.named()
+            .into_iter()
+            .map(|(name, heatmap)| {
+                let path = dir.join(format!("{name}.png"));
+                heatmap.save_png(&path)?;
+                Ok(path)
+            })
+            .collect()
+    }
+}
+
+/// Which heatmap is shown over the pitch, as an index into
+/// [`Heatmaps::named`].
+#[derive(Resource, Default)]
+struct HeatmapView(Option<usize>);
+
+#[derive(Component)]
+struct HeatmapOverlay;
+
+fn sample_positions(
+    mut heatmaps: ResMut<Heatmaps>,
+    mut ticks: Local<u32>,
+    balls: Query<&Transform, With<Ball>>,
+    players: Query<(&Team, &PlayerNumber, &Transform), With<Player>>,
+) {
+    *ticks += 1;
+    if *ticks % SAMPLE_TICKS != 0 {
+        return;
+    }
+
+    for ball in &balls {
+        heatmaps.ball.add(ball.translation.truncate());
+    }
+    for (team, number, transform) in &players {
+        heatmaps
+            .players
+            .entry((team.0, number.0))
+            .or_default()
+            .add(transform.translation.truncate());
+    }
+}
+
+/// `H` steps through the heatmaps over the pitch, then back to the game or
+/// the full-time stats. Each one shows the match up to when it came up, so
+/// they can be looked at mid-match too.
+#[allow(clippy::too_many_arguments)]
+fn cycle_heatmap(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut view: ResMut<HeatmapView>,
+    heatmaps: Res<Heatmaps>,
+    overlays: Query<Entity, With<HeatmapOverlay>>,
+    mut screens: Query<&mut Visibility, With<FullTimeScreen>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyH) {
+        return;
+    }
+
+    let named = heatmaps.named();
+    view.0 = match view.0 {
+        None => Some(0),
+        Some(index) if index + 1 < named.len() => Some(index + 1),
+        Some(_) => None,
+    };
+
+    for overlay in &overlays {
+        commands.entity(overlay).despawn_recursive();
+    }
+    for mut visibility in &mut screens {
+        *visibility = if view.0.is_some() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+    let Some((name, heatmap)) = view.0.map(|index| &named[index]) else {
+        return;
+    };
+
+    commands.spawn((
+        SpriteBundle {
+            texture: images.add(heatmap.to_image()),
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(GROUND_WIDTH, GROUND_HEIGHT)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0., GROUND_MIDDLE, OVERLAY_Z),
+            ..default()
+        },
+        HeatmapOverlay,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            format!("Heatmap: {name}   H next   X save PNGs"),
+            TextStyle {
+                color: Color::WHITE,
+                font_size: 16.,
+                font: asset_server.load("fonts/More 15 Basic.ttf"),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(4.),
+            left: Val::Px(4.),
+            ..default()
+        }),
+        HeatmapOverlay,
+        UI_LAYER,
+    ));
+}
+
+/// `X` saves every heatmap from the match so far to a new folder in
+/// [`HEATMAP_DIR`].
+fn export_heatmaps(keys: Res<ButtonInput<KeyCode>>, heatmaps: Res<Heatmaps>) {
+    if !keys.just_pressed(KeyCode::KeyX) {
+        return;
+    }
+
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let dir = Path::new(HEATMAP_DIR).join(format!("match-{stamp}"));
+    match heatmaps.export(&dir) {
+        Ok(files) => info!("Saved {} heatmaps to {}", files.len(), dir.display()),
+        Err(error) => warn!("Failed to save heatmaps to {}: {error}", dir.display()),
+    }
+}
+
+fn reset_heatmaps(
+    mut commands: Commands,
+    mut heatmaps: ResMut<Heatmaps>,
+    mut view: ResMut<HeatmapView>,
+    overlays: Query<Entity, With<HeatmapOverlay>>,
+) {
+    *heatmaps = default();
+    view.0 = None;
+    for overlay in &overlays {
+        commands.entity(overlay).despawn_recursive();
+    }
+}
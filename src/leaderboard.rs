@@ -0,0 +1,420 @@
+use std::{
+    cmp::Ordering,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::UI_LAYER,
+    clock::{FullTime, NewMatch},
+    mode::GameMode,
+    obstacle::{arena_name, ObstacleLayout},
+    player::{Versus, NUM_TEAMS},
+    save::SaveData,
+    stats::{start_new_match, MatchStats},
+};
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InitialsEntry>().add_systems(
+            Update,
+            (
+                offer_initials_entry.run_if(on_event::<FullTime>()),
+                enter_initials.before(start_new_match),
+                toggle_leaderboard,
+                close_screens.run_if(on_event::<NewMatch>()),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Places on the leaderboard.
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// Finished matches kept for the history, newest first.
+pub const HISTORY_SIZE: usize = 20;
+
+const INITIALS: usize = 3;
+
+/// How a finished match went, from the winner's side.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct MatchResult {
+    /// Filled in once the winner has entered them. Blank for results that
+    /// didn't make the table.
+    pub initials: String,
+    /// Goals scored by the winning team, or by either side in a draw.
+    pub goals: u32,
+    /// How many goals the winners won by.
+    pub margin: u32,
+    /// Seconds since the Unix epoch, when the match finished.
+    pub date: u64,
+    pub arena: ObstacleLayout,
+    pub mode: GameMode,
+    /// The profile the result counts for, if the active one's team won.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Penalties scored by the winners and then the losers, when a draw was
+    /// settled by a shootout.
+    #[serde(default)]
+    pub penalties: Option<(u32, u32)>,
+}
+
+impl MatchResult {
+    /// The result of a match that ended with `goals` scored by each team.
+    pub fn new(goals: [u32; NUM_TEAMS], arena: ObstacleLayout, mode: GameMode) -> Self {
+        let most = goals.iter().copied().max().unwrap_or(0);
+        let fewest = goals.iter().copied().min().unwrap_or(0);
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+
+        Self {
+            initials: String::new(),
+            goals: most,
+            margin: most - fewest,
+            date,
+            arena,
+            mode,
+            profile: None,
+            penalties: None,
+        }
+    }
+
+    /// Better results sort first: more goals, then a bigger margin, then
+    /// whoever got there first.
+    fn rank(&self, other: &Self) -> Ordering {
+        other
+            .goals
+            .cmp(&self.goals)
+            .then(other.margin.cmp(&self.margin))
+            .then(self.date.cmp(&other.date))
+    }
+
+    /// Like `3-1`, or `2-2 (4-3 pens)` for a draw settled by a shootout.
+    pub fn score(&self) -> String {
+        let score = format!("{}-{}", self.goals, self.goals - self.margin);
+        match self.penalties {
+            Some((won, lost)) => format!("{score} ({won}-{lost} pens)"),
+            None => score,
+        }
+    }
+
+    /// The day the match was played, as `YYYY-MM-DD` in UTC.
+    pub fn day(&self) -> String {
+        // Howard Hinnant's days-to-civil algorithm.
+        let days = (self.date / 86_400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+}
+
+/// The best results ever played, and the latest ones.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct HighScores {
+    /// Best first, at most `LEADERBOARD_SIZE`.
+    pub table: Vec<MatchResult>,
+    /// Newest first, at most `HISTORY_SIZE`.
+    pub history: Vec<MatchResult>,
+}
+
+impl HighScores {
+    pub fn best(&self) -> Option<&MatchResult> {
+        self.table.first()
+    }
+
+    /// Whether `result` would earn a place on the table.
+    pub fn qualifies(&self, result: &MatchResult) -> bool {
+        result.goals > 0
+            && (self.table.len() < LEADERBOARD_SIZE
+                || self
+                    .table
+                    .last()
+                    .is_some_and(|last| result.rank(last) == Ordering::Less))
+    }
+
+    /// Adds `result` to the history, and to the table if it qualifies.
+    /// Returns its place on the table, counting from 0.
+    pub fn record(&mut self, result: MatchResult) -> Option<usize> {
+        self.history.insert(0, result.clone());
+        self.history.truncate(HISTORY_SIZE);
+
+        if !self.qualifies(&result) {
+            return None;
+        }
+        let place = self
+            .table
+            .partition_point(|entry| entry.rank(&result) != Ordering::Greater);
+        self.table.insert(place, result);
+        self.table.truncate(LEADERBOARD_SIZE);
+        Some(place)
+    }
+
+    /// Swaps the latest result for `settled`, on the table as well if it
+    /// made it there. Its place doesn't change.
+    pub fn amend_latest(&mut self, settled: MatchResult) {
+        let Some(latest) = self.history.first_mut() else {
+            return;
+        };
+        let previous = std::mem::replace(latest, settled.clone());
+        if let Some(entry) = self.table.iter_mut().find(|entry| **entry == previous) {
+            *entry = settled;
+        }
+    }
+}
+
+/// The initials being entered for a result that made the table. Up and down
+/// change the letter under the cursor, left and right move it, and enter
+/// saves them.
+#[derive(Resource, Default)]
+pub struct InitialsEntry(Option<PendingEntry>);
+
+impl InitialsEntry {
+    pub fn is_active(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+struct PendingEntry {
+    result: MatchResult,
+    letters: [u8; INITIALS],
+    cursor: usize,
+}
+
+impl PendingEntry {
+    fn initials(&self) -> String {
+        self.letters.iter().map(|&letter| letter as char).collect()
+    }
+}
+
+#[derive(Component)]
+struct InitialsScreen;
+
+#[derive(Component)]
+struct LeaderboardScreen;
+
+fn text_style(asset_server: &AssetServer, font_size: f32) -> TextStyle {
+    TextStyle {
+        color: Color::WHITE,
+        font_size,
+        font: asset_server.load("fonts/More 15 Basic.ttf"),
+    }
+}
+
+/// Results that make the table wait for the winner's initials. The rest go
+/// straight into the history.
+#[allow(clippy::too_many_arguments)]
+fn offer_initials_entry(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut save: ResMut<SaveData>,
+    mut entry: ResMut<InitialsEntry>,
+    stats: Res<MatchStats>,
+    layout: Res<ObstacleLayout>,
+    mode: Res<State<GameMode>>,
+    versus: Res<Versus>,
+) {
+    let goals = stats.teams.each_ref().map(|team| team.goals);
+    let mut result = MatchResult::new(goals, layout.clone(), *mode.get());
+    save.attribute(&mut result, goals, *versus);
+
+    if !save.high_scores.qualifies(&result) {
+        save.high_scores.record(result);
+        return;
+    }
+
+    // Start from the profile's initials, so they only need confirming.
+    let mut letters = [b'A'; INITIALS];
+    if let Some(profile) = save.active_profile().filter(|_| result.profile.is_some()) {
+        for (letter, initial) in letters.iter_mut().zip(profile.initials().bytes()) {
+            *letter = initial;
+        }
+    }
+    entry.0 = Some(PendingEntry {
+        result,
+        letters,
+        cursor: 0,
+    });
+    commands.spawn((
+        TextBundle::from_section("", text_style(&asset_server, 28.))
+            .with_text_justify(JustifyText::Center)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(16.),
+                width: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                ..default()
+            }),
+        InitialsScreen,
+        UI_LAYER,
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn enter_initials(
+    mut commands: Commands,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut save: ResMut<SaveData>,
+    mut entry: ResMut<InitialsEntry>,
+    mut screens: Query<(Entity, &mut Text), With<InitialsScreen>>,
+    leaderboards: Query<(), With<LeaderboardScreen>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(pending) = &mut entry.0 else {
+        return;
+    };
+
+    let letter = &mut pending.letters[pending.cursor];
+    if keys.any_just_pressed([KeyCode::ArrowUp, KeyCode::KeyW]) {
+        *letter = if *letter == b'Z' { b'A' } else { *letter + 1 };
+    }
+    if keys.any_just_pressed([KeyCode::ArrowDown, KeyCode::KeyS]) {
+        *letter = if *letter == b'A' { b'Z' } else { *letter - 1 };
+    }
+    if keys.any_just_pressed([KeyCode::ArrowLeft, KeyCode::KeyA]) {
+        pending.cursor = pending.cursor.saturating_sub(1);
+    }
+    if keys.any_just_pressed([KeyCode::ArrowRight, KeyCode::KeyD]) {
+        pending.cursor = (pending.cursor + 1).min(INITIALS - 1);
+    }
+
+    if keys.just_pressed(KeyCode::Enter) {
+        // Enter also starts the next match, which shouldn't happen until
+        // the leaderboard has been seen.
+        keys.clear_just_pressed(KeyCode::Enter);
+
+        let mut result = pending.result.clone();
+        result.initials = pending.initials();
+        save.high_scores.record(result);
+        entry.0 = None;
+
+        for (screen, _) in &screens {
+            commands.entity(screen).despawn_recursive();
+        }
+        if leaderboards.is_empty() {
+            spawn_leaderboard(&mut commands, &asset_server, &save.high_scores);
+        }
+        return;
+    }
+
+    let cursor: String = (0..INITIALS)
+        .map(|index| if index == pending.cursor { '^' } else { ' ' })
+        .collect();
+    for (_, mut text) in &mut screens {
+        text.sections[0].value = format!(
+            "NEW HIGH SCORE {}\nEnter your initials\n{}\n{}",
+            pending.result.score(),
+            pending.initials(),
+            cursor
+        );
+    }
+}
+
+/// `L` shows the leaderboard, and hides it again.
+fn toggle_leaderboard(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    save: Res<SaveData>,
+    entry: Res<InitialsEntry>,
+    screens: Query<Entity, With<LeaderboardScreen>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyL) || entry.is_active() {
+        return;
+    }
+
+    if screens.is_empty() {
+        spawn_leaderboard(&mut commands, &asset_server, &save.high_scores);
+    }
+    for screen in &screens {
+        commands.entity(screen).despawn_recursive();
+    }
+}
+
+fn spawn_leaderboard(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    high_scores: &HighScores,
+) {
+    let describe = |result: &MatchResult| {
+        let arena = match &result.arena {
+            ObstacleLayout::Classic => "Classic".to_string(),
+            ObstacleLayout::Procedural { seed } => format!("Seed {seed}"),
+            ObstacleLayout::File(path) => arena_name(path),
+        };
+        format!(
+            "{:<3}  {:>5}  {}  {}  {:?}",
+            result.initials,
+            result.score(),
+            result.day(),
+            arena,
+            result.mode
+        )
+    };
+
+    let mut lines = vec!["HIGH SCORES".to_string()];
+    if high_scores.table.is_empty() {
+        lines.push("No records yet".to_string());
+    }
+    for (place, result) in high_scores.table.iter().enumerate() {
+        lines.push(format!("{:>2}. {}", place + 1, describe(result)));
+    }
+    lines.push(String::new());
+    lines.push("RECENT MATCHES".to_string());
+    for result in high_scores.history.iter().take(5) {
+        lines.push(format!("    {}", describe(result)));
+    }
+    lines.push(String::new());
+    lines.push("L close".to_string());
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.9).into(),
+                ..default()
+            },
+            LeaderboardScreen,
+            UI_LAYER,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                lines.join("\n"),
+                text_style(asset_server, 16.),
+            ));
+        });
+}
+
+fn close_screens(
+    mut commands: Commands,
+    mut entry: ResMut<InitialsEntry>,
+    screens: Query<Entity, Or<(With<InitialsScreen>, With<LeaderboardScreen>)>>,
+) {
+    entry.0 = None;
+    for screen in &screens {
+        commands.entity(screen).despawn_recursive();
+    }
+}
@@ -2,9 +2,32 @@ pub mod animation;
 pub mod arena;
 pub mod ball;
 pub mod camera;
+pub mod camera_effects;
+pub mod clock;
+pub mod config;
 pub mod constants;
+#[cfg(feature = "devtools")]
+pub mod devtools;
+pub mod editor;
+pub mod headless;
+pub mod heatmap;
+pub mod leaderboard;
+pub mod match_log;
+pub mod menu;
+pub mod minimap;
+pub mod mode;
 pub mod obstacle;
 pub mod player;
+pub mod procedural;
+pub mod profile;
+pub mod recording;
+pub mod replay;
+pub mod save;
+pub mod scenario;
+pub mod shootout;
+pub mod simulation;
+pub mod stats;
+pub mod suspend;
 pub mod ui;
 mod utils;
 
@@ -13,8 +36,28 @@ pub mod prelude {
     pub use crate::arena::ArenaPlugin;
     pub use crate::ball::BallPlugin;
     pub use crate::camera::CameraPlugin;
+    pub use crate::camera_effects::CameraEffectsPlugin;
+    pub use crate::clock::ClockPlugin;
     pub use crate::constants::*;
+    #[cfg(feature = "devtools")]
+    pub use crate::devtools::DevToolsPlugin;
+    pub use crate::editor::LevelEditorPlugin;
+    pub use crate::heatmap::HeatmapPlugin;
+    pub use crate::leaderboard::LeaderboardPlugin;
+    pub use crate::match_log::MatchLogPlugin;
+    pub use crate::menu::MenuPlugin;
+    pub use crate::minimap::MinimapPlugin;
+    pub use crate::mode::{GameMode, ModePlugin};
     pub use crate::obstacle::ObstaclePlugin;
     pub use crate::player::PlayerPlugin;
+    pub use crate::procedural::ProceduralPlugin;
+    pub use crate::profile::ProfilePlugin;
+    pub use crate::recording::RecordingPlugin;
+    pub use crate::replay::ReplayPlugin;
+    pub use crate::save::SavePlugin;
+    pub use crate::shootout::ShootoutPlugin;
+    pub use crate::simulation::SimulationPlugin;
+    pub use crate::stats::StatsPlugin;
+    pub use crate::suspend::SuspendPlugin;
     pub use crate::ui::UIPlugin;
 }
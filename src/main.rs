@@ -1,16 +1,43 @@
-use bevy::{asset::AssetMetaCheck, prelude::*};
+use std::{env, process};
+
+use bevy::{asset::AssetMetaCheck, prelude::*, window::WindowMode};
 use bevy_ecs_tilemap::TilemapPlugin;
-use bevy_editor_pls::prelude::*;
 use bevy_embedded_assets::{EmbeddedAssetPlugin, PluginMode};
 use bevy_kira_audio::prelude::*;
 use bevy_pkv::PkvStore;
 use bevy_rapier2d::prelude::*;
 
-use bevy_soccer::prelude::*;
+use bevy_soccer::{
+    config::{ConfigError, LaunchConfig},
+    headless::HeadlessApp,
+    prelude::*,
+    save::SaveData,
+};
 
 fn main() {
-    App::new()
-        .insert_resource(AssetMetaCheck::Never) // Makes WASM happy
+    let config = match LaunchConfig::from_args(env::args().skip(1)) {
+        Ok(config) => config,
+        Err(ConfigError::Help) => {
+            println!("{}", ConfigError::Help);
+            return;
+        }
+        Err(error) => {
+            eprintln!("error: {error}\n\nRun with --help to see the options.");
+            process::exit(2);
+        }
+    };
+
+    if config.headless {
+        let mut game = HeadlessApp::with_config(&config);
+        game.play_to_full_time();
+        let score = game.world().resource::<SaveData>().score;
+        println!("Full time. Score: {score}");
+        return;
+    }
+
+    let mut app = App::new();
+    config.apply(&mut app);
+    app.insert_resource(AssetMetaCheck::Never) // Makes WASM happy
         .insert_resource(ClearColor(Color::BLACK))
         .insert_resource(PkvStore::new("kennethlove", "soccer-game"))
         .add_plugins(EmbeddedAssetPlugin {
@@ -21,8 +48,13 @@ fn main() {
                 .set(WindowPlugin {
                     primary_window: Some(Window {
                         title: "Soccer Game".to_string(),
-                        resolution: (WINDOW_WIDTH, WINDOW_HEIGHT).into(),
-                        resizable: false,
+                        resolution: (config.window.0 as f32, config.window.1 as f32).into(),
+                        mode: if config.fullscreen {
+                            WindowMode::BorderlessFullscreen
+                        } else {
+                            WindowMode::Windowed
+                        },
+                        resizable: true,
                         ..default()
                     }),
                     ..default()
@@ -31,20 +63,39 @@ fn main() {
         )
         .add_plugins(AudioPlugin) // Kira audio
         .add_plugins(TilemapPlugin) // ECS tilemap
-        .add_plugins((
-            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.),
-            RapierDebugRenderPlugin::default(),
-        ))
-        // .add_plugins(EditorPlugin::default())
+        .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.).in_fixed_schedule())
         .add_plugins((
             bevy_soccer::animation::AnimationPlugin,
             ArenaPlugin,
             BallPlugin,
             CameraPlugin,
+            CameraEffectsPlugin,
+            ClockPlugin,
+            HeatmapPlugin,
+            LeaderboardPlugin,
+            LevelEditorPlugin,
+            MatchLogPlugin,
+            MenuPlugin,
+            MinimapPlugin,
+            ModePlugin,
+        ))
+        .add_plugins((
             ObstaclePlugin,
             PlayerPlugin,
+            ProceduralPlugin,
+            ProfilePlugin,
+            RecordingPlugin,
+            ReplayPlugin,
+            SavePlugin,
+            ShootoutPlugin,
+            SimulationPlugin,
+            StatsPlugin,
+            SuspendPlugin,
             UIPlugin,
-        ))
-        .add_systems(Update, bevy::window::close_on_esc)
-        .run();
+        ));
+
+    #[cfg(feature = "devtools")]
+    app.add_plugins(DevToolsPlugin);
+
+    app.run();
 }
@@ -0,0 +1,303 @@
+//! Writes what happens in each match to a JSON Lines file, for analysing
+//! games away from the game.
+//!
+//! Each match gets its own `match-<unix time in ms>.jsonl` in the chosen
+//! directory, opened at the first event and closed at full time. Every line
+//! is one [`LogEntry`]. The format is meant to be read by other tools, so
+//! fields and events may be added but existing ones keep their names and
+//! meanings.
+
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{LineWriter, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::{attacking_team, Goal, GoalEvent, Wall},
+    ball::{track_last_touch, Ball, LastTouch},
+    clock::{run_clock, FullTime, MatchClock},
+    mode::GameMode,
+    obstacle::{ball_collision, ObstacleVariant},
+    player::{Player, PlayerNumber, Team},
+    simulation::GameplaySet,
+    stats::Shot,
+};
+
+pub struct MatchLogPlugin;
+
+impl Plugin for MatchLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchLog>().add_systems(
+            FixedUpdate,
+            (
+                log_kickoffs,
+                log_possession_changes,
+                log_shots,
+                log_goals,
+                log_ball_collisions,
+                log_full_time,
+            )
+                .chain()
+                .after(run_clock)
+                .after(track_last_touch)
+                .in_set(GameplaySet)
+                .run_if(in_state(GameMode::Match).and_then(match_log_enabled)),
+        );
+    }
+}
+
+/// One line of the log.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct LogEntry {
+    /// Fixed ticks since the match kicked off. There are `SIMULATION_HZ` to
+    /// the second.
+    pub tick: u32,
+    /// Where the ball was, in pixels from the centre of the arena with y
+    /// pointing up. Goals give the goal's position if the ball has already
+    /// gone.
+    pub position: Option<[f32; 2]>,
+    #[serde(flatten)]
+    pub event: MatchEvent,
+}
+
+/// Written as an `"event"` field naming the variant in snake case, next to
+/// the variant's own fields.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MatchEvent {
+    /// A ball was put in play at the centre, at the start and after goals.
+    Kickoff,
+    /// A player from the other team touched the ball, or the first player
+    /// since kickoff did.
+    PossessionChange { player: PlayerId },
+    /// A touch sent the ball toward the other team's goal at shooting speed.
+    Shot { player: PlayerId, on_target: bool },
+    /// `team` is the one the goal counts for. `scorer` is whoever touched
+    /// the ball last, which might be someone on the other team.
+    Goal {
+        team: usize,
+        scorer: Option<PlayerId>,
+    },
+    /// The ball hit an obstacle. `obstacle` is the variant's name in snake
+    /// case, like `"bumper"`.
+    ObstacleHit { obstacle: String },
+    /// The ball hit one of the walls around the arena.
+    WallBounce,
+    /// A period of play ended. Matches are played in a single period, so
+    /// this is always 1, at full time.
+    PeriodEnd { period: u32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PlayerId {
+    pub team: usize,
+    /// Counts from 0 within the team.
+    pub number: usize,
+}
+
+/// Where to write the logs. Nothing is logged without a directory.
+#[derive(Resource, Default)]
+pub struct MatchLog {
+    dir: Option<PathBuf>,
+    file: Option<LineWriter<File>>,
+}
+
+impl MatchLog {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self { dir, file: None }
+    }
+
+    fn write(&mut self, entry: &LogEntry) -> Result<(), Box<dyn Error>> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                fs::create_dir_all(dir)?;
+                let started = SystemTime::now().duration_since(UNIX_EPOCH)?;
+                let path = dir.join(format!("match-{}.jsonl", started.as_millis()));
+                self.file.insert(LineWriter::new(File::create(path)?))
+            }
+        };
+        serde_json::to_writer(&mut *file, entry)?;
+        writeln!(file)?;
+        Ok(())
+    }
+
+    /// Stops logging at the first failure rather than warning on every
+    /// event after it.
+    fn log(&mut self, tick: u32, position: Option<Vec2>, event: MatchEvent) {
+        let entry = LogEntry {
+            tick,
+            position: position.map(|position| position.to_array()),
+            event,
+        };
+        if let Err(error) = self.write(&entry) {
+            warn!("Stopped writing the match log: {error}");
+            self.dir = None;
+            self.file = None;
+        }
+    }
+
+    /// The next event starts a new file.
+    fn close(&mut self) {
+        self.file = None;
+    }
+}
+
+fn match_log_enabled(log: Res<MatchLog>) -> bool {
+    log.dir.is_some()
+}
+
+fn player_id(
+    players: &Query<(&Team, &PlayerNumber), With<Player>>,
+    player: Entity,
+) -> Option<PlayerId> {
+    players.get(player).ok().map(|(team, number)| PlayerId {
+        team: team.0,
+        number: number.0,
+    })
+}
+
+fn ball_position(balls: &Query<&Transform, With<Ball>>) -> Option<Vec2> {
+    balls.iter().next().map(|ball| ball.translation.truncate())
+}
+
+fn log_kickoffs(
+    mut log: ResMut<MatchLog>,
+    clock: Res<MatchClock>,
+    balls: Query<&Transform, Added<Ball>>,
+) {
+    for ball in &balls {
+        log.log(
+            clock.tick(),
+            Some(ball.translation.truncate()),
+            MatchEvent::Kickoff,
+        );
+    }
+}
+
+/// `previous` is the team last on the ball, forgotten when a goal clears
+/// `LastTouch`.
+fn log_possession_changes(
+    mut log: ResMut<MatchLog>,
+    mut previous: Local<Option<usize>>,
+    clock: Res<MatchClock>,
+    last_touch: Res<LastTouch>,
+    balls: Query<&Transform, With<Ball>>,
+    players: Query<(&Team, &PlayerNumber), With<Player>>,
+) {
+    let Some(player) = last_touch.0.and_then(|player| player_id(&players, player)) else {
+        *previous = None;
+        return;
+    };
+    if *previous == Some(player.team) {
+        return;
+    }
+
+    *previous = Some(player.team);
+    log.log(
+        clock.tick(),
+        ball_position(&balls),
+        MatchEvent::PossessionChange { player },
+    );
+}
+
+fn log_shots(
+    mut log: ResMut<MatchLog>,
+    mut shots: EventReader<Shot>,
+    clock: Res<MatchClock>,
+    balls: Query<&Transform, With<Ball>>,
+    players: Query<(&Team, &PlayerNumber), With<Player>>,
+) {
+    for shot in shots.read() {
+        if let Some(player) = player_id(&players, shot.player) {
+            log.log(
+                clock.tick(),
+                ball_position(&balls),
+                MatchEvent::Shot {
+                    player,
+                    on_target: shot.on_target,
+                },
+            );
+        }
+    }
+}
+
+fn log_goals(
+    mut log: ResMut<MatchLog>,
+    mut goal_events: EventReader<GoalEvent>,
+    clock: Res<MatchClock>,
+    balls: Query<&Transform, With<Ball>>,
+    goals: Query<&Transform, With<Goal>>,
+    players: Query<(&Team, &PlayerNumber), With<Player>>,
+) {
+    for goal_event in goal_events.read() {
+        let Ok(goal) = goals.get(goal_event.goal) else {
+            continue;
+        };
+        let position = ball_position(&balls).unwrap_or(goal.translation.truncate());
+        let scorer = goal_event
+            .scorer
+            .and_then(|scorer| player_id(&players, scorer));
+
+        log.log(
+            clock.tick(),
+            Some(position),
+            MatchEvent::Goal {
+                team: attacking_team(goal.translation),
+                scorer,
+            },
+        );
+    }
+}
+
+fn log_ball_collisions(
+    mut log: ResMut<MatchLog>,
+    mut collision_events: EventReader<CollisionEvent>,
+    clock: Res<MatchClock>,
+    balls: Query<Entity, With<Ball>>,
+    positions: Query<&Transform, With<Ball>>,
+    walls: Query<(), With<Wall>>,
+    obstacles: Query<&ObstacleVariant>,
+) {
+    for collision_event in collision_events.read() {
+        let Some((_, other)) = ball_collision(collision_event, &balls) else {
+            continue;
+        };
+        let event = if walls.contains(other) {
+            MatchEvent::WallBounce
+        } else if let Ok(variant) = obstacles.get(other) {
+            MatchEvent::ObstacleHit {
+                obstacle: variant.name().to_string(),
+            }
+        } else {
+            continue;
+        };
+        log.log(clock.tick(), ball_position(&positions), event);
+    }
+}
+
+fn log_full_time(
+    mut log: ResMut<MatchLog>,
+    mut full_time: EventReader<FullTime>,
+    clock: Res<MatchClock>,
+    balls: Query<&Transform, With<Ball>>,
+) {
+    for _ in full_time.read() {
+        log.log(
+            clock.tick(),
+            ball_position(&balls),
+            MatchEvent::PeriodEnd { period: 1 },
+        );
+        log.close();
+    }
+}
@@ -0,0 +1,359 @@
+use bevy::{app::AppExit, input::InputSystem, prelude::*};
+
+use crate::{
+    camera::UI_LAYER,
+    mode::{GameMode, Simulation},
+    player::{Bindings, Versus, NUM_TEAMS},
+    profile::{Profile, MAX_NAME_LENGTH},
+    save::SaveData,
+    shootout::Shootout,
+};
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Menu>()
+            .add_systems(PreUpdate, control_menu.after(InputSystem))
+            .add_systems(
+                Update,
+                update_menu_screen
+                    .run_if(resource_changed::<Menu>.or_else(resource_changed::<SaveData>)),
+            );
+    }
+}
+
+/// The pause menu. `Esc` opens it over whatever is going on, and while it's
+/// open it has the keyboard to itself.
+#[derive(Resource, Default)]
+pub struct Menu {
+    open: bool,
+    selected: usize,
+    editing: Option<Editing>,
+    /// Whether closing the menu should start the simulation again. It stays
+    /// paused if it already was, like at full time or in the editor.
+    resume: bool,
+}
+
+impl Menu {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+enum Editing {
+    /// Typing the name of a new profile.
+    Name(String),
+    /// Pressing the new keys, in the order of `BINDING_NAMES`.
+    Keys(Vec<KeyCode>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MenuItem {
+    Resume,
+    Shootout,
+    Profile,
+    NewProfile,
+    Kit,
+    Controls,
+    Quit,
+}
+
+const ITEMS: [MenuItem; 7] = [
+    MenuItem::Resume,
+    MenuItem::Shootout,
+    MenuItem::Profile,
+    MenuItem::NewProfile,
+    MenuItem::Kit,
+    MenuItem::Controls,
+    MenuItem::Quit,
+];
+
+const BINDING_NAMES: [&str; 5] = ["up", "down", "left", "right", "run"];
+
+/// Keys that do something else mid-match, so can't be bound to a player.
+const RESERVED_KEYS: [KeyCode; 25] = [
+    KeyCode::Escape,
+    KeyCode::Enter,
+    KeyCode::Space,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Home,
+    KeyCode::KeyH,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyP,
+    KeyCode::KeyR,
+    KeyCode::KeyX,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+];
+
+#[derive(Component)]
+struct MenuScreen;
+
+/// Runs straight after input is read, and clears it while the menu is open
+/// so nothing else reacts to keys meant for the menu.
+#[allow(clippy::too_many_arguments)]
+fn control_menu(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut menu: ResMut<Menu>,
+    mut save: ResMut<SaveData>,
+    mut simulation: Simulation,
+    mut exit: EventWriter<AppExit>,
+    mode: Res<State<GameMode>>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+    mut shootout: ResMut<Shootout>,
+    versus: Res<Versus>,
+) {
+    let typed: String = characters.read().map(|event| event.char.as_str()).collect();
+
+    if !menu.open {
+        if keys.just_pressed(KeyCode::Escape) {
+            menu.open = true;
+            menu.selected = 0;
+            menu.resume = !simulation.is_paused();
+            simulation.pause();
+            keys.reset_all();
+        }
+        return;
+    }
+
+    match &mut menu.editing {
+        Some(Editing::Name(name)) => {
+            if keys.just_pressed(KeyCode::Escape) {
+                menu.editing = None;
+            } else if keys.just_pressed(KeyCode::Enter) && !name.trim().is_empty() {
+                save.profiles.push(Profile::new(name.trim()));
+                save.active_profile = Some(save.profiles.len() - 1);
+                menu.editing = None;
+            } else if keys.just_pressed(KeyCode::Backspace) {
+                name.pop();
+            } else {
+                for character in typed.chars() {
+                    if (character.is_alphanumeric() || character == ' ')
+                        && name.chars().count() < MAX_NAME_LENGTH
+                    {
+                        name.push(character);
+                    }
+                }
+            }
+            keys.reset_all();
+            return;
+        }
+        Some(Editing::Keys(pressed)) => {
+            if keys.just_pressed(KeyCode::Escape) {
+                menu.editing = None;
+            } else if let Some(&key) = keys.get_just_pressed().find(|key| {
+                let taken_by_other_team = save.active_profile().is_some_and(|profile| {
+                    (0..NUM_TEAMS)
+                        .filter(|&team| team != profile.team(*versus))
+                        .any(|team| save.bindings(team, *versus).keys().contains(key))
+                });
+                !pressed.contains(key) && !RESERVED_KEYS.contains(key) && !taken_by_other_team
+            }) {
+                pressed.push(key);
+                if let [up, down, left, right, run] = pressed[..] {
+                    if let Some(profile) = save.active_profile_mut() {
+                        profile.bindings = Bindings {
+                            up,
+                            down,
+                            left,
+                            right,
+                            run,
+                        };
+                    }
+                    menu.editing = None;
+                }
+            }
+            keys.reset_all();
+            return;
+        }
+        None => {}
+    }
+
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        menu.selected = (menu.selected + ITEMS.len() - 1) % ITEMS.len();
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        menu.selected = (menu.selected + 1) % ITEMS.len();
+    }
+
+    let step = if keys.just_pressed(KeyCode::ArrowLeft) {
+        Some(-1)
+    } else if keys.just_pressed(KeyCode::ArrowRight) {
+        Some(1)
+    } else {
+        None
+    };
+    let confirm = keys.just_pressed(KeyCode::Enter);
+    let has_profile = save.active_profile().is_some();
+
+    // Penalties on their own end the match being played.
+    let start_shootout =
+        ITEMS[menu.selected] == MenuItem::Shootout && confirm && *mode.get() == GameMode::Match;
+    if start_shootout {
+        *shootout = Shootout::new(false);
+        next_mode.set(GameMode::Shootout);
+    }
+
+    match ITEMS[menu.selected] {
+        MenuItem::Profile => {
+            if let Some(step) = step {
+                // Guest comes before the first profile.
+                let choices = save.profiles.len() as i32 + 1;
+                let current = save.active_profile.map_or(0, |index| index as i32 + 1);
+                let next = (current + step).rem_euclid(choices);
+                save.active_profile = (next > 0).then(|| next as usize - 1);
+            }
+        }
+        MenuItem::NewProfile if confirm => menu.editing = Some(Editing::Name(String::new())),
+        MenuItem::Kit if has_profile && (confirm || step.is_some()) => {
+            if let Some(profile) = save.active_profile_mut() {
+                profile.change_kit();
+            }
+        }
+        MenuItem::Controls if has_profile && confirm => {
+            menu.editing = Some(Editing::Keys(Vec::new()));
+        }
+        MenuItem::Quit if confirm => {
+            exit.send(AppExit);
+        }
+        _ => {}
+    }
+
+    let close = keys.just_pressed(KeyCode::Escape)
+        || (ITEMS[menu.selected] == MenuItem::Resume && confirm)
+        || start_shootout;
+    if close {
+        menu.open = false;
+        menu.editing = None;
+        if menu.resume {
+            simulation.resume();
+        }
+    }
+    keys.reset_all();
+}
+
+fn update_menu_screen(
+    mut commands: Commands,
+    menu: Res<Menu>,
+    save: Res<SaveData>,
+    mut screens: Query<(Entity, &mut Text), With<MenuScreen>>,
+    asset_server: Res<AssetServer>,
+) {
+    if !menu.open {
+        for (screen, _) in &screens {
+            commands.entity(screen).despawn_recursive();
+        }
+        return;
+    }
+
+    let text = menu_text(&menu, &save);
+    if let Ok((_, mut existing)) = screens.get_single_mut() {
+        existing.sections[0].value = text;
+        return;
+    }
+
+    let style = TextStyle {
+        color: Color::WHITE,
+        font_size: 16.,
+        font: asset_server.load("fonts/More 15 Basic.ttf"),
+    };
+    commands.spawn((
+        TextBundle::from_section(text, style)
+            .with_text_justify(JustifyText::Center)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                top: Val::Percent(25.),
+                ..default()
+            })
+            .with_background_color(Color::rgba(0., 0., 0., 0.9)),
+        MenuScreen,
+        UI_LAYER,
+    ));
+}
+
+fn menu_text(menu: &Menu, save: &SaveData) -> String {
+    let profile = save.active_profile();
+
+    match &menu.editing {
+        Some(Editing::Name(name)) => {
+            return format!("NEW PROFILE\nType a name\n{name}_\n\nENTER create   ESC cancel");
+        }
+        Some(Editing::Keys(pressed)) => {
+            let name = profile.map_or("", |profile| &profile.name);
+            return format!(
+                "CONTROLS FOR {}\nPress the key for {}\n\nHotkeys and the other team's keys are skipped\nESC cancel",
+                name.to_uppercase(),
+                BINDING_NAMES[pressed.len()]
+            );
+        }
+        None => {}
+    }
+
+    let label = |item: MenuItem| match item {
+        MenuItem::Resume => "Resume".to_string(),
+        MenuItem::Shootout => "Penalty shootout".to_string(),
+        MenuItem::Profile => {
+            format!(
+                "Profile: < {} >",
+                profile.map_or("Guest", |profile| &profile.name)
+            )
+        }
+        MenuItem::NewProfile => "New profile".to_string(),
+        MenuItem::Kit => format!("Kit: {}", profile.map_or("-", Profile::kit_name)),
+        MenuItem::Controls => match profile {
+            Some(profile) => {
+                let keys: Vec<String> = profile
+                    .bindings
+                    .keys()
+                    .iter()
+                    .map(|key| format!("{key:?}"))
+                    .collect();
+                format!("Controls: {}", keys.join(" "))
+            }
+            None => "Controls: -".to_string(),
+        },
+        MenuItem::Quit => "Quit".to_string(),
+    };
+
+    let mut lines = vec!["PAUSED".to_string(), String::new()];
+    for (index, &item) in ITEMS.iter().enumerate() {
+        let marker = if index == menu.selected { ">" } else { " " };
+        lines.push(format!("{marker} {}", label(item)));
+    }
+    lines.push(String::new());
+    match profile {
+        Some(profile) => {
+            let stats = &profile.stats;
+            lines.push(format!(
+                "Played {}  Won {}  Drawn {}  Lost {}",
+                stats.played, stats.won, stats.drawn, stats.lost
+            ));
+            lines.push(format!(
+                "Goals {}-{}  Most in a match {}  Biggest win {}",
+                stats.goals_for, stats.goals_against, stats.most_goals, stats.biggest_win
+            ));
+            lines.push(format!(
+                "Shootouts won {}  lost {}",
+                stats.shootouts_won, stats.shootouts_lost
+            ));
+        }
+        None => lines.push("Guests' results aren't kept".to_string()),
+    }
+    lines.join("\n")
+}
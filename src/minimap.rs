@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+
+use crate::{
+    arena::{Goal, GOAL_HEIGHT, GOAL_WIDTH},
+    ball::Ball,
+    camera::{CameraBounds, MainCamera, UI_LAYER},
+    constants::*,
+    obstacle::ObstacleVariant,
+    player::{Player, PlayerType, Team, TEAM_COLORS},
+};
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Minimap>()
+            .init_gizmo_group::<MinimapGizmos>()
+            .add_systems(Startup, configure_minimap_gizmos)
+            .add_systems(
+                Update,
+                (
+                    toggle_minimap,
+                    draw_minimap.run_if(|minimap: Res<Minimap>| minimap.visible),
+                ),
+            );
+    }
+}
+
+/// Whether the radar in the top right corner is shown. `M` toggles it.
+#[derive(Resource)]
+pub struct Minimap {
+    pub visible: bool,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self { visible: true }
+    }
+}
+
+/// Kept apart from the default gizmos so the minimap is only drawn by the
+/// HUD camera and never shows up in the world.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct MinimapGizmos;
+
+const MINIMAP_WIDTH: f32 = 160.;
+const MINIMAP_MARGIN: f32 = 10.;
+const DOT_RADIUS: f32 = 2.;
+const LIVE_RING_RADIUS: f32 = 4.;
+
+const PITCH_COLOR: Color = Color::rgba(1., 1., 1., 0.8);
+const VIEW_COLOR: Color = Color::rgba(1., 1., 1., 0.3);
+const BALL_COLOR: Color = Color::WHITE;
+
+fn configure_minimap_gizmos(mut config_store: ResMut<GizmoConfigStore>) {
+    let (config, _) = config_store.config_mut::<MinimapGizmos>();
+    config.render_layers = UI_LAYER;
+    config.line_width = 1.;
+}
+
+fn toggle_minimap(keys: Res<ButtonInput<KeyCode>>, mut minimap: ResMut<Minimap>) {
+    if keys.just_pressed(KeyCode::KeyM) {
+        minimap.visible = !minimap.visible;
+    }
+}
+
+/// Maps the whole of `CameraBounds` onto a small rectangle in the corner of
+/// the HUD camera's view.
+struct MinimapTransform {
+    bounds: Rect,
+    centre: Vec2,
+    scale: f32,
+}
+
+impl MinimapTransform {
+    fn new(bounds: Rect) -> Self {
+        let scale = MINIMAP_WIDTH / bounds.width();
+        let size = bounds.size() * scale;
+        let corner = Vec2::new(VIRTUAL_WIDTH, VIRTUAL_HEIGHT) / 2. - MINIMAP_MARGIN;
+
+        Self {
+            bounds,
+            centre: corner - size / 2.,
+            scale,
+        }
+    }
+
+    fn point(&self, world: Vec2) -> Vec2 {
+        self.centre + (world - self.bounds.center()) * self.scale
+    }
+
+    fn size(&self, world: Vec2) -> Vec2 {
+        world * self.scale
+    }
+}
+
+fn draw_minimap(
+    mut gizmos: Gizmos<MinimapGizmos>,
+    bounds: Res<CameraBounds>,
+    cameras: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    goals: Query<&Transform, With<Goal>>,
+    obstacles: Query<(&Transform, &Sprite, &ObstacleVariant)>,
+    balls: Query<&Transform, With<Ball>>,
+    players: Query<(&Transform, &Team, &PlayerType), With<Player>>,
+) {
+    let map = MinimapTransform::new(bounds.0);
+
+    gizmos.rect_2d(
+        map.point(Vec2::new(0., GROUND_MIDDLE)),
+        0.,
+        map.size(Vec2::new(GROUND_WIDTH, GROUND_HEIGHT)),
+        PITCH_COLOR,
+    );
+
+    if let Ok((camera, projection)) = cameras.get_single() {
+        let view = Vec2::new(VIRTUAL_WIDTH, VIRTUAL_HEIGHT) * projection.scale;
+        gizmos.rect_2d(
+            map.point(camera.translation.truncate()),
+            0.,
+            map.size(view),
+            VIEW_COLOR,
+        );
+    }
+
+    for goal in &goals {
+        gizmos.rect_2d(
+            map.point(goal.translation.truncate()),
+            goal.rotation.to_euler(EulerRot::XYZ).2,
+            map.size(Vec2::new(GOAL_WIDTH, GOAL_HEIGHT)),
+            Color::PINK,
+        );
+    }
+
+    for (obstacle, sprite, variant) in &obstacles {
+        let size = sprite.custom_size.unwrap_or(Vec2::ONE) * obstacle.scale.truncate();
+        gizmos.rect_2d(
+            map.point(obstacle.translation.truncate()),
+            obstacle.rotation.to_euler(EulerRot::XYZ).2,
+            map.size(size),
+            variant.color(),
+        );
+    }
+
+    for ball in &balls {
+        gizmos.circle_2d(
+            map.point(ball.translation.truncate()),
+            DOT_RADIUS,
+            BALL_COLOR,
+        );
+    }
+
+    for (player, team, player_type) in &players {
+        let position = map.point(player.translation.truncate());
+        let color = TEAM_COLORS[team.0 % TEAM_COLORS.len()];
+
+        gizmos.circle_2d(position, DOT_RADIUS, color);
+        if *player_type == PlayerType::Live {
+            gizmos.circle_2d(position, LIVE_RING_RADIUS, Color::WHITE);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::MatchClock;
+
+pub struct ModePlugin;
+
+impl Plugin for ModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<GameMode>();
+    }
+}
+
+/// What the game is currently being used for.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, States, Deserialize, Serialize)]
+pub enum GameMode {
+    #[default]
+    Match,
+    Editor,
+    /// Playing back the moments before a goal.
+    Replay,
+    /// Watching a whole match loaded from a recording.
+    Viewer,
+    /// Penalties, on their own or to settle a drawn match.
+    Shootout,
+}
+
+/// Virtual time and Rapier, which stop and start together.
+#[derive(SystemParam)]
+pub(crate) struct Simulation<'w> {
+    time: ResMut<'w, Time<Virtual>>,
+    rapier_config: ResMut<'w, RapierConfiguration>,
+}
+
+impl Simulation<'_> {
+    pub(crate) fn is_paused(&self) -> bool {
+        self.time.is_paused()
+    }
+
+    /// Freezes the match: virtual time stops and Rapier no longer steps, so
+    /// anything moved by hand stays where it is put.
+    pub(crate) fn pause(&mut self) {
+        self.time.pause();
+        self.rapier_config.physics_pipeline_active = false;
+    }
+
+    pub(crate) fn resume(&mut self) {
+        self.time.unpause();
+        self.rapier_config.physics_pipeline_active = true;
+    }
+}
+
+pub(crate) fn pause_simulation(mut simulation: Simulation) {
+    simulation.pause();
+}
+
+pub(crate) fn resume_simulation(mut simulation: Simulation) {
+    simulation.resume();
+}
+
+/// For leaving a mode that paused the match: a match that has reached full
+/// time stays frozen on its result.
+pub(crate) fn resume_unless_full_time(mut simulation: Simulation, clock: Res<MatchClock>) {
+    if !clock.is_full_time() {
+        simulation.resume();
+    }
+}
@@ -1,19 +1,68 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::constants::*;
+use crate::{
+    arena::{default_goals, spawn_goal, ArenaFile, Goal, GoalPlacement},
+    ball::{Ball, BALL_RADIUS},
+    constants::*,
+    player::TeamSize,
+    procedural,
+    simulation::{GameplaySet, Interpolated},
+};
 
 pub struct ObstaclePlugin;
 
 impl Plugin for ObstaclePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_obstacles)
-            .add_systems(Update, (spin_obstacles, expand_obstacles));
+        app.init_resource::<ObstacleLayout>()
+            .add_systems(
+                Update,
+                spawn_obstacles.run_if(resource_changed::<ObstacleLayout>),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    spin_obstacles,
+                    expand_obstacles,
+                    patrol_obstacles,
+                    bump_ball,
+                    teleport_ball,
+                    tick_teleport_cooldown,
+                    break_obstacles,
+                )
+                    .in_set(GameplaySet),
+            );
     }
 }
 
-#[derive(Component)]
-enum ObstacleVariant {
+/// Which set of obstacles the arena is built from. Changing it rebuilds them.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub enum ObstacleLayout {
+    #[default]
+    Classic,
+    Procedural {
+        seed: u64,
+    },
+    /// An arena saved by the level editor, goals and all.
+    File(PathBuf),
+}
+
+/// What an arena file is called on screen: its name without the extension.
+pub(crate) fn arena_name(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[derive(Component, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ObstacleVariant {
     Static,
     Spinning {
         clockwise: bool,
@@ -24,102 +73,316 @@ enum ObstacleVariant {
         min_scale: f32,
         expanding: bool,
         horizontal: bool,
+        scale: f32,
+    },
+    /// Walks the waypoints (offsets from the obstacle's spawn point) in a loop,
+    /// easing in and out of each one.
+    Patrol {
+        waypoints: Cow<'static, [Vec2]>,
+        speed: f32,
+        leg: usize,
+        progress: f32,
     },
+    /// Kicks the ball away from its centre on contact.
+    Bumper {
+        strength: f32,
+    },
+    /// Moves the ball to the other teleporter with the same `pair`.
+    Teleporter {
+        pair: u8,
+    },
+    /// Despawns once the ball has hit it `hits` times.
+    Breakable {
+        hits: u32,
+    },
+}
+
+impl ObstacleVariant {
+    pub(crate) fn color(&self) -> Color {
+        match self {
+            ObstacleVariant::Patrol { .. } => Color::ORANGE_RED,
+            ObstacleVariant::Bumper { .. } => Color::YELLOW,
+            ObstacleVariant::Teleporter { .. } => Color::PURPLE,
+            ObstacleVariant::Breakable { .. } => Color::GRAY,
+            _ => Color::RED,
+        }
+    }
+
+    /// The variant in snake case, for logs.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ObstacleVariant::Static => "static",
+            ObstacleVariant::Spinning { .. } => "spinning",
+            ObstacleVariant::Expanding { .. } => "expanding",
+            ObstacleVariant::Patrol { .. } => "patrol",
+            ObstacleVariant::Bumper { .. } => "bumper",
+            ObstacleVariant::Teleporter { .. } => "teleporter",
+            ObstacleVariant::Breakable { .. } => "breakable",
+        }
+    }
+
+    /// Obstacles that move are kinematic so Rapier derives their velocity from
+    /// the transform changes and passes it on to the ball. Expanding ones only
+    /// change shape, which Rapier can't see as motion, so `expand_obstacles`
+    /// pushes the ball itself.
+    fn rigid_body(&self) -> RigidBody {
+        match self {
+            ObstacleVariant::Spinning { .. }
+            | ObstacleVariant::Expanding { .. }
+            | ObstacleVariant::Patrol { .. } => RigidBody::KinematicPositionBased,
+            _ => RigidBody::Fixed,
+        }
+    }
 }
 
+/// Where the obstacle was spawned, used as the origin for patrol waypoints.
 #[derive(Component)]
-struct Obstacle {
-    translation: Vec3,
-    variant: ObstacleVariant,
+pub(crate) struct ObstacleAnchor(pub(crate) Vec3);
+
+/// Stops the ball bouncing straight back through the portal it arrived at.
+#[derive(Component)]
+struct TeleportCooldown(Timer);
+
+#[derive(Component, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Obstacle {
+    pub translation: Vec3,
+    /// Radians around the z axis.
+    #[serde(default)]
+    pub rotation: f32,
+    pub variant: ObstacleVariant,
 }
 
 impl Default for Obstacle {
     fn default() -> Self {
         Self {
             translation: Vec3::ZERO,
+            rotation: 0.,
             variant: ObstacleVariant::Static,
         }
     }
 }
 
-const OBSTACLE_SIZE: f32 = 40.;
+pub(crate) const OBSTACLE_SIZE: f32 = 40.;
 
-const OBSTACLES: [Obstacle; 6] = [
+pub(crate) const PATROL_VERTICAL: [Vec2; 2] = [Vec2::new(0., -50.), Vec2::new(0., 50.)];
+
+const OBSTACLES: [Obstacle; 14] = [
     Obstacle {
         translation: Vec3::new(
-            -WINDOW_WIDTH / 3.,
+            -VIRTUAL_WIDTH / 3.,
             (-GROUND_HEIGHT / 4.) + GROUND_OFFSET.y,
             5.,
         ), // bottom left
+        rotation: 0.,
         variant: ObstacleVariant::Static,
     },
     Obstacle {
         translation: Vec3::new(
-            WINDOW_WIDTH / 3.,
+            VIRTUAL_WIDTH / 3.,
             (-GROUND_HEIGHT / 4.) + GROUND_OFFSET.y,
             5.,
         ), // bottom right
+        rotation: 0.,
         variant: ObstacleVariant::Static,
     },
     Obstacle {
         translation: Vec3::new(
-            -WINDOW_WIDTH / 3.,
+            -VIRTUAL_WIDTH / 3.,
             (GROUND_HEIGHT / 4.) + GROUND_OFFSET.y,
             5.,
         ), // top left
+        rotation: 0.,
         variant: ObstacleVariant::Spinning { clockwise: true },
     },
     Obstacle {
         translation: Vec3::new(
-            WINDOW_WIDTH / 3.,
+            VIRTUAL_WIDTH / 3.,
             (GROUND_HEIGHT / 4.) + GROUND_OFFSET.y,
             4.,
         ), // top right
+        rotation: 0.,
         variant: ObstacleVariant::Spinning { clockwise: false },
     },
     Obstacle {
         translation: Vec3::new(0., (GROUND_HEIGHT / 4.) + GROUND_OFFSET.y, 4.), // bottom center
+        rotation: 0.,
         variant: ObstacleVariant::Expanding {
             speed: 0.5,
             max_scale: 2.,
             min_scale: 1.,
             expanding: true,
             horizontal: true,
+            scale: 1.,
         },
     },
     Obstacle {
         translation: Vec3::new(0., (-GROUND_HEIGHT / 4.) + GROUND_OFFSET.y, 4.), // top center
+        rotation: 0.,
         variant: ObstacleVariant::Expanding {
             speed: 0.5,
             max_scale: 2.,
             min_scale: 1.,
             expanding: true,
             horizontal: false,
+            scale: 1.,
+        },
+    },
+    Obstacle {
+        translation: Vec3::new(-VIRTUAL_WIDTH / 6., GROUND_MIDDLE, 4.), // middle left
+        rotation: 0.,
+        variant: ObstacleVariant::Patrol {
+            waypoints: Cow::Borrowed(&PATROL_VERTICAL),
+            speed: 60.,
+            leg: 0,
+            progress: 0.,
+        },
+    },
+    Obstacle {
+        translation: Vec3::new(VIRTUAL_WIDTH / 6., GROUND_MIDDLE, 4.), // middle right
+        rotation: 0.,
+        variant: ObstacleVariant::Patrol {
+            waypoints: Cow::Borrowed(&PATROL_VERTICAL),
+            speed: 60.,
+            leg: 1,
+            progress: 0.,
         },
     },
+    Obstacle {
+        translation: Vec3::new(
+            VIRTUAL_WIDTH / 6.,
+            (GROUND_HEIGHT * 3. / 8.) + GROUND_OFFSET.y,
+            4.,
+        ), // top right
+        rotation: 0.,
+        variant: ObstacleVariant::Bumper { strength: 400. },
+    },
+    Obstacle {
+        translation: Vec3::new(
+            -VIRTUAL_WIDTH / 6.,
+            (-GROUND_HEIGHT * 3. / 8.) + GROUND_OFFSET.y,
+            4.,
+        ), // bottom left
+        rotation: 0.,
+        variant: ObstacleVariant::Bumper { strength: 400. },
+    },
+    Obstacle {
+        translation: Vec3::new(
+            -VIRTUAL_WIDTH / 6.,
+            (GROUND_HEIGHT * 3. / 8.) + GROUND_OFFSET.y,
+            4.,
+        ), // top left
+        rotation: 0.,
+        variant: ObstacleVariant::Teleporter { pair: 0 },
+    },
+    Obstacle {
+        translation: Vec3::new(
+            VIRTUAL_WIDTH / 6.,
+            (-GROUND_HEIGHT * 3. / 8.) + GROUND_OFFSET.y,
+            4.,
+        ), // bottom right
+        rotation: 0.,
+        variant: ObstacleVariant::Teleporter { pair: 0 },
+    },
+    Obstacle {
+        translation: Vec3::new(-VIRTUAL_WIDTH * 5. / 12., GROUND_MIDDLE, 4.), // left goal mouth
+        rotation: 0.,
+        variant: ObstacleVariant::Breakable { hits: 3 },
+    },
+    Obstacle {
+        translation: Vec3::new(VIRTUAL_WIDTH * 5. / 12., GROUND_MIDDLE, 4.), // right goal mouth
+        rotation: 0.,
+        variant: ObstacleVariant::Breakable { hits: 3 },
+    },
 ];
 
-fn spawn_obstacles(mut commands: Commands) {
-    for obstacle in OBSTACLES {
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform::from_translation(obstacle.translation),
-                sprite: Sprite {
-                    color: Color::RED,
-                    custom_size: Some(Vec2::new(OBSTACLE_SIZE, OBSTACLE_SIZE)),
-                    ..default()
-                },
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn spawn_obstacles(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    layout: Res<ObstacleLayout>,
+    team_size: Res<TeamSize>,
+    existing: Query<Entity, With<ObstacleVariant>>,
+    goals: Query<(Entity, &Transform), With<Goal>>,
+    balls: Query<Entity, With<Ball>>,
+) {
+    // Rebuilding mid-match could leave the ball inside a new obstacle, so
+    // drop it and let the ball plugin serve a fresh one.
+    if !existing.is_empty() {
+        for entity in existing.iter().chain(balls.iter()) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    let classic = || ArenaFile {
+        goals: default_goals(),
+        obstacles: Vec::from(OBSTACLES),
+    };
+    let arena = match &*layout {
+        ObstacleLayout::Classic => classic(),
+        ObstacleLayout::Procedural { seed } => ArenaFile {
+            goals: default_goals(),
+            obstacles: procedural::generate(*seed, team_size.0),
+        },
+        ObstacleLayout::File(path) => ArenaFile::load(path).unwrap_or_else(|error| {
+            error!("Failed to load arena from {}: {}", path.display(), error);
+            classic()
+        }),
+    };
+
+    // Only arena files move the goals, so they're usually left standing.
+    let placed: Vec<GoalPlacement> = goals
+        .iter()
+        .map(|(_, transform)| GoalPlacement {
+            translation: transform.translation,
+            rotation: transform.rotation.to_euler(EulerRot::XYZ).2,
+        })
+        .collect();
+    if placed != arena.goals {
+        for (entity, _) in &goals {
+            commands.entity(entity).despawn();
+        }
+        for goal in &arena.goals {
+            spawn_goal(&mut commands, &mut meshes, &mut materials, goal);
+        }
+    }
+
+    for obstacle in arena.obstacles {
+        spawn_obstacle(&mut commands, obstacle);
+    }
+}
+
+pub(crate) fn spawn_obstacle(commands: &mut Commands, obstacle: Obstacle) -> Entity {
+    let is_teleporter = matches!(obstacle.variant, ObstacleVariant::Teleporter { .. });
+    let rigid_body = obstacle.variant.rigid_body();
+    let transform = Transform::from_translation(obstacle.translation)
+        .with_rotation(Quat::from_rotation_z(obstacle.rotation));
+    let mut entity = commands.spawn((
+        SpriteBundle {
+            transform,
+            sprite: Sprite {
+                color: obstacle.variant.color(),
+                custom_size: Some(Vec2::new(OBSTACLE_SIZE, OBSTACLE_SIZE)),
                 ..default()
             },
-            Collider::cuboid(OBSTACLE_SIZE / 2., OBSTACLE_SIZE / 2.),
-            RigidBody::Fixed,
-            obstacle.variant,
-            Restitution {
-                coefficient: 1.,
-                combine_rule: CoefficientCombineRule::Max,
-            },
-        ));
+            ..default()
+        },
+        Collider::cuboid(OBSTACLE_SIZE / 2., OBSTACLE_SIZE / 2.),
+        rigid_body,
+        obstacle.variant,
+        ObstacleAnchor(obstacle.translation),
+        Interpolated::new(transform),
+        Restitution {
+            coefficient: 1.,
+            combine_rule: CoefficientCombineRule::Max,
+        },
+    ));
+
+    if is_teleporter {
+        entity.insert(Sensor);
     }
+
+    entity.id()
 }
 
 fn spin_obstacles(mut query: Query<(&ObstacleVariant, &mut Transform)>, time: Res<Time>) {
@@ -132,54 +395,240 @@ fn spin_obstacles(mut query: Query<(&ObstacleVariant, &mut Transform)>, time: Re
     }
 }
 
+/// Grows and shrinks the obstacle by scaling its transform, which Rapier
+/// applies to the collider so the physics shape always matches what is drawn.
+///
+/// Rapier only derives a kinematic body's velocity from how it moves, not from
+/// its shape changing, so a growing edge pushes the ball itself.
 fn expand_obstacles(
-    mut commands: Commands,
-    mut query: Query<(Entity, &ObstacleVariant, &mut Transform)>,
+    mut query: Query<(&mut ObstacleVariant, &mut Transform), Without<Ball>>,
+    mut balls: Query<(&Transform, &mut Velocity), With<Ball>>,
     time: Res<Time>,
 ) {
-    for (entity, variant, mut position) in query.iter_mut() {
+    for (mut variant, mut transform) in query.iter_mut() {
         if let ObstacleVariant::Expanding {
             speed,
             max_scale,
             min_scale,
             expanding,
             horizontal,
-        } = variant
+            scale,
+        } = variant.as_mut()
         {
-            let mut now_expanding = *expanding;
-            if *horizontal {
-                if *expanding && position.scale.x <= *max_scale {
-                    position.scale.x += time.delta_seconds() * speed;
-                    if position.scale.x >= *max_scale {
-                        now_expanding = false;
-                    }
-                } else if !expanding && position.scale.x >= *min_scale {
-                    position.scale.x -= time.delta_seconds() * speed;
-                    if position.scale.x <= *min_scale {
-                        now_expanding = true;
-                    }
+            if *expanding {
+                *scale += time.delta_seconds() * *speed;
+                if *scale >= *max_scale {
+                    *scale = *max_scale;
+                    *expanding = false;
                 }
             } else {
-                if *expanding && position.scale.y <= *max_scale {
-                    position.scale.y += time.delta_seconds() * speed;
-                    if position.scale.y >= *max_scale {
-                        now_expanding = false;
-                    }
-                } else if !expanding && position.scale.y >= *min_scale {
-                    position.scale.y -= time.delta_seconds() * speed;
-                    if position.scale.y <= *min_scale {
-                        now_expanding = true;
+                *scale -= time.delta_seconds() * *speed;
+                if *scale <= *min_scale {
+                    *scale = *min_scale;
+                    *expanding = true;
+                }
+            }
+
+            let axis = if *horizontal { Vec2::X } else { Vec2::Y };
+            transform.scale = (Vec2::ONE + axis * (*scale - 1.)).extend(1.);
+
+            if *expanding {
+                let edge_speed = *speed * OBSTACLE_SIZE / 2.;
+                for (ball, mut velocity) in &mut balls {
+                    if let Some(linvel) =
+                        pushed_off_growing_edge(&transform, axis, edge_speed, ball, velocity.linvel)
+                    {
+                        velocity.linvel = linvel;
                     }
                 }
             }
-            let new_variant = ObstacleVariant::Expanding {
-                speed: *speed,
-                max_scale: *max_scale,
-                min_scale: *min_scale,
-                expanding: now_expanding,
-                horizontal: *horizontal,
-            };
-            commands.entity(entity).insert(new_variant);
+        }
+    }
+}
+
+/// The velocity of a ball touching one of the growing edges once it's moving
+/// away from the obstacle at least as fast as the edge, if it wasn't already.
+fn pushed_off_growing_edge(
+    obstacle: &Transform,
+    axis: Vec2,
+    edge_speed: f32,
+    ball: &Transform,
+    linvel: Vec2,
+) -> Option<Vec2> {
+    let offset =
+        (obstacle.rotation.inverse() * (ball.translation - obstacle.translation)).truncate();
+    let half_size = obstacle.scale.truncate() * OBSTACLE_SIZE / 2.;
+
+    let along = offset.dot(axis);
+    let across = offset.dot(axis.perp());
+    let touching = along.abs() <= half_size.dot(axis) + BALL_RADIUS
+        && across.abs() <= half_size.dot(axis.perp()).abs();
+    if !touching {
+        return None;
+    }
+
+    let outwards = (obstacle.rotation * (axis * along.signum()).extend(0.)).truncate();
+    let speed = linvel.dot(outwards);
+    (speed < edge_speed).then(|| linvel + outwards * (edge_speed - speed))
+}
+
+fn patrol_obstacles(
+    mut query: Query<(&mut ObstacleVariant, &ObstacleAnchor, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (mut variant, anchor, mut position) in query.iter_mut() {
+        if let ObstacleVariant::Patrol {
+            waypoints,
+            speed,
+            leg,
+            progress,
+        } = variant.as_mut()
+        {
+            if waypoints.len() < 2 {
+                continue;
+            }
+
+            let from = waypoints[*leg % waypoints.len()];
+            let to = waypoints[(*leg + 1) % waypoints.len()];
+            let length = from.distance(to).max(f32::EPSILON);
+
+            *progress += time.delta_seconds() * *speed / length;
+            if *progress >= 1. {
+                *progress -= 1.;
+                *leg = (*leg + 1) % waypoints.len();
+                position.translation = anchor.0 + to.extend(0.);
+                continue;
+            }
+
+            // Smoothstep so the obstacle slows into each waypoint.
+            let eased = *progress * *progress * (3. - 2. * *progress);
+            position.translation = anchor.0 + from.lerp(to, eased).extend(0.);
+        }
+    }
+}
+
+/// Returns `(ball, other)` when a collision involving the ball has just started.
+pub(crate) fn ball_collision(
+    collision_event: &CollisionEvent,
+    balls: &Query<Entity, With<Ball>>,
+) -> Option<(Entity, Entity)> {
+    if let CollisionEvent::Started(entity1, entity2, _flags) = collision_event {
+        if balls.contains(*entity1) {
+            return Some((*entity1, *entity2));
+        }
+        if balls.contains(*entity2) {
+            return Some((*entity2, *entity1));
+        }
+    }
+    None
+}
+
+fn bump_ball(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    balls: Query<Entity, With<Ball>>,
+    obstacles: Query<(&ObstacleVariant, &Transform)>,
+    transforms: Query<&Transform, With<Ball>>,
+) {
+    for collision_event in collision_events.read() {
+        let Some((ball, obstacle)) = ball_collision(collision_event, &balls) else {
+            continue;
+        };
+
+        if let (Ok((ObstacleVariant::Bumper { strength }, bumper)), Ok(ball_position)) =
+            (obstacles.get(obstacle), transforms.get(ball))
+        {
+            let away = (ball_position.translation - bumper.translation)
+                .truncate()
+                .normalize_or_zero();
+            commands.entity(ball).try_insert(ExternalImpulse {
+                impulse: away * *strength,
+                torque_impulse: 0.,
+            });
+        }
+    }
+}
+
+/// Rapier keeps a body's velocity when its transform is moved, so the ball
+/// leaves the exit portal on the same heading it entered with.
+fn teleport_ball(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    balls: Query<Entity, With<Ball>>,
+    cooling_down: Query<(), With<TeleportCooldown>>,
+    portals: Query<(Entity, &ObstacleVariant)>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for collision_event in collision_events.read() {
+        let Some((ball, portal)) = ball_collision(collision_event, &balls) else {
+            continue;
+        };
+        if cooling_down.contains(ball) {
+            continue;
+        }
+        let Ok((_, ObstacleVariant::Teleporter { pair })) = portals.get(portal) else {
+            continue;
+        };
+
+        let exit = portals.iter().find_map(|(entity, variant)| match variant {
+            ObstacleVariant::Teleporter { pair: other } if other == pair && entity != portal => {
+                Some(entity)
+            }
+            _ => None,
+        });
+        let Some(exit) = exit else {
+            continue;
+        };
+        let Ok(exit_translation) = transforms.get(exit).map(|exit| exit.translation) else {
+            continue;
+        };
+
+        if let Ok(mut position) = transforms.get_mut(ball) {
+            position.translation.x = exit_translation.x;
+            position.translation.y = exit_translation.y;
+        }
+        commands
+            .entity(ball)
+            .try_insert(TeleportCooldown(Timer::from_seconds(0.5, TimerMode::Once)));
+    }
+}
+
+fn tick_teleport_cooldown(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut TeleportCooldown)>,
+    time: Res<Time>,
+) {
+    for (entity, mut cooldown) in &mut query {
+        cooldown.0.tick(time.delta());
+
+        if cooldown.0.finished() {
+            commands.entity(entity).remove::<TeleportCooldown>();
+        }
+    }
+}
+
+fn break_obstacles(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    balls: Query<Entity, With<Ball>>,
+    mut obstacles: Query<(&mut ObstacleVariant, &mut Sprite)>,
+) {
+    for collision_event in collision_events.read() {
+        let Some((_, obstacle)) = ball_collision(collision_event, &balls) else {
+            continue;
+        };
+
+        if let Ok((mut variant, mut sprite)) = obstacles.get_mut(obstacle) {
+            if let ObstacleVariant::Breakable { hits } = variant.as_mut() {
+                *hits = hits.saturating_sub(1);
+                if *hits == 0 {
+                    commands.entity(obstacle).despawn();
+                } else {
+                    // Fade a little with every hit so players can see it weakening.
+                    let alpha = sprite.color.a() * 0.6;
+                    sprite.color.set_a(alpha);
+                }
+            }
         }
     }
 }
@@ -1,10 +1,17 @@
 use crate::{
     animation::{AnimationIndices, AnimationTimer},
     constants::*,
+    mode::GameMode,
+    simulation::{interpolate_transforms, GameplaySet, Interpolated},
+};
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    transform::TransformSystem,
 };
-use bevy::{prelude::*, sprite::{MaterialMesh2dBundle, Mesh2dHandle}};
 use bevy_rapier2d::prelude::*;
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
 
 const WALK_SPEED: f32 = 150.;
 const RUN_SPEED: f32 = 300.;
@@ -16,8 +23,10 @@ const RUN_FRAMES: AnimationIndices = AnimationIndices {
     last: 23,
 };
 
-const PLAYERS_PER_TEAM: usize = 3;
-const NUM_TEAMS: usize = 1;
+pub(crate) const PLAYERS_PER_TEAM: usize = 3;
+/// Any more and the last player would line up inside the top wall.
+pub(crate) const MAX_PLAYERS_PER_TEAM: usize = 4;
+pub(crate) const NUM_TEAMS: usize = 2;
 
 #[derive(Clone, Component, Copy, Debug, Default, Eq, Hash, PartialEq, States)]
 enum PlayerState {
@@ -34,32 +43,49 @@ enum Direction {
 }
 
 #[derive(Component, PartialEq)]
-enum PlayerType {
+pub(crate) enum PlayerType {
     Live,
     Drone,
 }
 
+/// Points at the live player it was spawned for.
 #[derive(Component)]
-struct Marker;
+pub(crate) struct Marker(Entity);
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<PlayerState>()
+            .init_resource::<TeamSize>()
+            .init_resource::<Versus>()
             .add_event::<PlayerMoves>()
             .add_plugins(InputManagerPlugin::<PlayerAction>::default())
             .add_systems(Startup, (spawn_players, spawn_chosen_player_marker.after(spawn_players)))
             .add_systems(
                 FixedUpdate,
-                (player_idles, player_moves, update_sprite_direction),
+                (
+                    player_idles,
+                    // Recorded matches supply their own moves, and penalties
+                    // have their own controls.
+                    player_moves.run_if(
+                        not(in_state(GameMode::Viewer)).and_then(not(in_state(GameMode::Shootout))),
+                    ),
+                    movement.after(player_moves),
+                    update_sprite_direction,
+                )
+                    .in_set(GameplaySet),
+            )
+            .add_systems(
+                PostUpdate,
+                update_chosen_player_marker_position
+                    .after(interpolate_transforms)
+                    .before(TransformSystem::TransformPropagate),
             )
             .add_systems(
                 Update,
                 (
-                    movement,
                     update_direction,
-                    update_chosen_player_marker_position.after(movement),
                     idle_animation.run_if(in_state(PlayerState::Idle)),
                     walk_animation.run_if(in_state(PlayerState::Walking)),
                     run_animation.run_if(in_state(PlayerState::Running)),
@@ -68,7 +94,7 @@ impl Plugin for PlayerPlugin {
     }
 }
 
-#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect, Deserialize, Serialize)]
 pub enum PlayerAction {
     // Movement
     Idle,
@@ -104,6 +130,40 @@ impl PlayerAction {
 #[derive(Component)]
 pub struct Player;
 
+/// Which side a player is on. Team 0 kicks off on the left.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Team(pub usize);
+
+/// A player's place in their team's line-up, counting from 0.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PlayerNumber(pub usize);
+
+/// How many players line up on each side.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TeamSize(pub usize);
+
+impl Default for TeamSize {
+    fn default() -> Self {
+        Self(PLAYERS_PER_TEAM)
+    }
+}
+
+/// Whether a second human plays the other team. Otherwise the first team has
+/// the pitch to itself.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Versus(pub bool);
+
+impl Versus {
+    /// How many teams take the pitch.
+    pub fn teams(self) -> usize {
+        if self.0 {
+            NUM_TEAMS
+        } else {
+            1
+        }
+    }
+}
+
 #[derive(Bundle)]
 struct PlayerBundle {
     sprite_bundle: SpriteSheetBundle,
@@ -111,9 +171,22 @@ struct PlayerBundle {
     input_manager: InputManagerBundle<PlayerAction>,
     direction: Direction,
     player_type: PlayerType,
+    team: Team,
+    number: PlayerNumber,
 }
 
-const PLAYER_STARTING_POS: Vec3 = Vec3::new(-WINDOW_WIDTH / 4., GROUND_MIDDLE, 5.);
+const PLAYER_STARTING_POS: Vec3 = Vec3::new(-VIRTUAL_WIDTH / 4., GROUND_MIDDLE, 5.);
+
+/// Where a player lines up at kickoff. The second team lines up as a mirror
+/// image of the first.
+pub(crate) fn starting_position(team: usize, player: usize) -> Vec3 {
+    let side = if team % 2 == 0 { 1. } else { -1. };
+    Vec3::new(
+        PLAYER_STARTING_POS.x * side,
+        PLAYER_STARTING_POS.y + (player as f32 * 48.),
+        PLAYER_STARTING_POS.z,
+    )
+}
 
 impl PlayerBundle {
     fn default() -> Self {
@@ -129,53 +202,115 @@ impl PlayerBundle {
                 ..default()
             },
             player: Player,
-            input_manager: InputManagerBundle::with_map(PlayerBundle::default_input_map()),
+            input_manager: InputManagerBundle::with_map(Bindings::for_team(0).input_map(0)),
             direction: Direction::Right,
             player_type: PlayerType::Drone,
+            team: Team(0),
+            number: PlayerNumber(0),
+        }
+    }
+}
+
+/// The keys a team plays with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Bindings {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub run: KeyCode,
+}
+
+impl Bindings {
+    /// The left team plays on WASD, the right team on the arrow keys.
+    pub fn for_team(team: usize) -> Self {
+        if team == 0 {
+            Self {
+                up: KeyCode::KeyW,
+                down: KeyCode::KeyS,
+                left: KeyCode::KeyA,
+                right: KeyCode::KeyD,
+                run: KeyCode::ShiftLeft,
+            }
+        } else {
+            Self {
+                up: KeyCode::ArrowUp,
+                down: KeyCode::ArrowDown,
+                left: KeyCode::ArrowLeft,
+                right: KeyCode::ArrowRight,
+                run: KeyCode::ShiftRight,
+            }
         }
     }
 
-    fn default_input_map() -> InputMap<PlayerAction> {
+    pub fn keys(&self) -> [KeyCode; 5] {
+        [self.up, self.down, self.left, self.right, self.run]
+    }
+
+    /// These keys, with `team`'s gamepad as well.
+    pub(crate) fn input_map(&self, team: usize) -> InputMap<PlayerAction> {
         use PlayerAction::*;
         let mut input_map = InputMap::default();
+        self.insert_keys(&mut input_map);
 
         // Movement
-        input_map.insert(Up, KeyCode::ArrowUp);
-        input_map.insert(Up, KeyCode::KeyW);
         input_map.insert(Up, GamepadButtonType::DPadUp);
-
-        input_map.insert(Down, KeyCode::ArrowDown);
-        input_map.insert(Down, KeyCode::KeyS);
         input_map.insert(Down, GamepadButtonType::DPadDown);
-
-        input_map.insert(Left, KeyCode::ArrowLeft);
-        input_map.insert(Left, KeyCode::KeyA);
         input_map.insert(Left, GamepadButtonType::DPadLeft);
-
-        input_map.insert(Right, KeyCode::ArrowRight);
-        input_map.insert(Right, KeyCode::KeyD);
         input_map.insert(Right, GamepadButtonType::DPadRight);
 
         // Actions
-        input_map.insert(Run, KeyCode::ShiftLeft);
-        input_map.insert(Run, KeyCode::ShiftRight);
         input_map.insert(Run, GamepadButtonType::East);
 
+        input_map.set_gamepad(Gamepad::new(team));
+
         input_map
     }
+
+    fn insert_keys(&self, input_map: &mut InputMap<PlayerAction>) {
+        use PlayerAction::*;
+        input_map.insert(Up, self.up);
+        input_map.insert(Down, self.down);
+        input_map.insert(Left, self.left);
+        input_map.insert(Right, self.right);
+        input_map.insert(Run, self.run);
+    }
+}
+
+/// What `team` plays with. Someone playing alone can use either team's keys.
+pub(crate) fn team_input_map(
+    bindings: Bindings,
+    team: usize,
+    versus: Versus,
+) -> InputMap<PlayerAction> {
+    let mut input_map = bindings.input_map(team);
+    if !versus.0 {
+        for other in (0..NUM_TEAMS).filter(|&other| other != team) {
+            Bindings::for_team(other).insert_keys(&mut input_map);
+        }
+    }
+    input_map
 }
 
+const TEAM_SPRITES: [&str; NUM_TEAMS] = ["sprites/blue.png", "sprites/red.png"];
+pub(crate) const TEAM_NAMES: [&str; NUM_TEAMS] = ["Blue", "Red"];
+pub(crate) const TEAM_COLORS: [Color; NUM_TEAMS] =
+    [Color::rgb(0.3, 0.5, 1.), Color::rgb(1., 0.3, 0.3)];
+
 fn spawn_players(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    team_size: Res<TeamSize>,
+    versus: Res<Versus>,
 ) {
-    let texture: Handle<Image> = asset_server.load("sprites/blue.png");
     let layout = TextureAtlasLayout::from_grid(Vec2::new(24., 24.), 24, 1, None, None);
     let texture_atlas_layout = texture_atlas_layouts.add(layout);
 
-    for team in 0..NUM_TEAMS {
-        for player in 0..PLAYERS_PER_TEAM {
+    for team in 0..versus.teams() {
+        let texture: Handle<Image> = asset_server.load(TEAM_SPRITES[team]);
+
+        for player in 0..team_size.0 {
             let mut new_player = PlayerBundle::default();
             new_player.sprite_bundle.texture = texture.clone();
             new_player.sprite_bundle.sprite.color = Color::WHITE;
@@ -183,13 +318,16 @@ fn spawn_players(
                 layout: texture_atlas_layout.clone(),
                 index: IDLE_FRAMES.first,
             };
-            new_player.sprite_bundle.transform.translation = Vec3::new(
-                PLAYER_STARTING_POS.x + (team as f32 * 48.),
-                PLAYER_STARTING_POS.y + (player as f32 * 48.),
-                PLAYER_STARTING_POS.z,
-            );
+            new_player.sprite_bundle.transform.translation = starting_position(team, player);
             let player_type = if player == 0 { PlayerType::Live } else { PlayerType::Drone };
             new_player.player_type = player_type;
+            new_player.team = Team(team);
+            new_player.number = PlayerNumber(player);
+            new_player.input_manager = InputManagerBundle::with_map(team_input_map(
+                Bindings::for_team(team),
+                team,
+                *versus,
+            ));
 
             commands.spawn((
                 new_player,
@@ -202,6 +340,7 @@ fn spawn_players(
                 },
                 RigidBody::KinematicPositionBased,
                 Collider::cuboid(12., 17.),
+                Interpolated::new(Transform::from_translation(starting_position(team, player))),
             ));
         }
     }
@@ -209,7 +348,7 @@ fn spawn_players(
 
 fn spawn_chosen_player_marker(
     mut commands: Commands,
-    query: Query<(&PlayerType, &Transform), With<PlayerType>>,
+    query: Query<(Entity, &PlayerType, &Transform)>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
@@ -217,9 +356,9 @@ fn spawn_chosen_player_marker(
         return;
     }
 
-    for (pt, player) in &query {
+    for (entity, pt, player) in &query {
         if pt != &PlayerType::Live {
-            return;
+            continue;
         }
 
         let pointer = Mesh2dHandle(meshes.add(Triangle2d::new(
@@ -234,14 +373,15 @@ fn spawn_chosen_player_marker(
             ..default()
         };
 
-        commands.spawn((marker, Marker));
+        commands.spawn((marker, Marker(entity)));
     }
 }
 
 #[derive(Debug, Default, Event)]
-struct PlayerMoves {
-    direction: Option<Direction2d>,
-    running: bool,
+pub(crate) struct PlayerMoves {
+    pub(crate) player: Option<Entity>,
+    pub(crate) direction: Option<Direction2d>,
+    pub(crate) running: bool,
 }
 
 fn player_idles(
@@ -259,13 +399,16 @@ fn player_idles(
     next_state.set(PlayerState::Idle);
 }
 
-fn player_moves(
-    query: Query<&ActionState<PlayerAction>, With<Player>>,
+pub(crate) fn player_moves(
+    query: Query<(Entity, &PlayerType, &ActionState<PlayerAction>), With<Player>>,
     mut event_writer: EventWriter<PlayerMoves>,
 ) {
-    let mut direction_vector = Vec2::ZERO;
+    for (entity, player_type, action_state) in &query {
+        if player_type != &PlayerType::Live {
+            continue;
+        }
 
-    for action_state in &query {
+        let mut direction_vector = Vec2::ZERO;
         for input_direction in PlayerAction::DIRECTIONS {
             if action_state.pressed(&input_direction) {
                 if let Some(direction) = input_direction.direction() {
@@ -278,6 +421,7 @@ fn player_moves(
 
         if let Ok(direction) = net_direction {
             event_writer.send(PlayerMoves {
+                player: Some(entity),
                 direction: Some(direction),
                 running: action_state.pressed(&PlayerAction::Run),
             });
@@ -285,7 +429,7 @@ fn player_moves(
     }
 }
 
-fn movement(
+pub(crate) fn movement(
     mut query: Query<&mut KinematicCharacterController, With<Player>>,
     mut player_moves: EventReader<PlayerMoves>,
     mut next_state: ResMut<NextState<PlayerState>>,
@@ -295,25 +439,29 @@ fn movement(
         return;
     }
 
-    for mut player in &mut query {
-        for event in player_moves.read() {
-            let PlayerMoves { direction, running } = event;
-            {
-                if *running {
-                    next_state.set(PlayerState::Running);
-                } else {
-                    next_state.set(PlayerState::Walking);
-                }
+    for event in player_moves.read() {
+        let PlayerMoves {
+            player,
+            direction,
+            running,
+        } = event;
+        let Some(mut player) = player.and_then(|player| query.get_mut(player).ok()) else {
+            continue;
+        };
 
-                if let Some(direction) = direction {
-                    player.translation = {
-                        Some(
-                            Vec2::new(direction.x, direction.y)
-                                * time.delta_seconds()
-                                * if *running { RUN_SPEED } else { WALK_SPEED },
-                        )
-                    }
-                }
+        if *running {
+            next_state.set(PlayerState::Running);
+        } else {
+            next_state.set(PlayerState::Walking);
+        }
+
+        if let Some(direction) = direction {
+            player.translation = {
+                Some(
+                    Vec2::new(direction.x, direction.y)
+                        * time.delta_seconds()
+                        * if *running { RUN_SPEED } else { WALK_SPEED },
+                )
             }
         }
     }
@@ -401,21 +549,12 @@ fn update_sprite_direction(mut query: Query<(&mut Sprite, &Direction), With<Play
 }
 
 fn update_chosen_player_marker_position(
-    query: Query<(&PlayerType, &Transform), Without<Marker>>,
-    mut marker_query: Query<&mut Transform, With<Marker>>,
+    query: Query<&Transform, (With<Player>, Without<Marker>)>,
+    mut marker_query: Query<(&Marker, &mut Transform)>,
 ) {
-    if query.is_empty() || marker_query.is_empty() {
-        return;
-    }
-
-    for (pt, player) in &query {
-
-        if pt != &PlayerType::Live {
-            return;
+    for (marker, mut transform) in &mut marker_query {
+        if let Ok(player) = query.get(marker.0) {
+            transform.translation = player.translation + Vec3::new(0., 30., 0.);
         }
-
-        let mut marker = marker_query.get_single_mut().unwrap();
-
-        marker.translation = player.translation + Vec3::new(0., 30., 0.);
     }
 }
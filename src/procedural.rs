@@ -0,0 +1,273 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    arena::GOAL_HEIGHT,
+    constants::*,
+    mode::GameMode,
+    obstacle::{Obstacle, ObstacleLayout, ObstacleVariant, OBSTACLE_SIZE},
+    player::{starting_position, NUM_TEAMS},
+};
+
+pub struct ProceduralPlugin;
+
+impl Plugin for ProceduralPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SeedEntry>()
+            .init_resource::<LayoutRng>()
+            .add_systems(
+                Update,
+                (switch_layout, enter_seed).run_if(in_state(GameMode::Match)),
+            );
+    }
+}
+
+/// The digits typed so far while a seed is being entered.
+#[derive(Resource, Default)]
+pub struct SeedEntry(pub Option<String>);
+
+/// Rolls the seeds for new procedural layouts. Seeding it makes a whole
+/// session's layouts repeatable.
+#[derive(Resource)]
+pub struct LayoutRng(StdRng);
+
+impl LayoutRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+
+    pub fn next_seed(&mut self) -> u64 {
+        self.0.gen_range(0..1_000_000)
+    }
+}
+
+impl Default for LayoutRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+// The pitch is split into obstacle-sized cells. Obstacles are placed in whole
+// cells so the path check below can work on the grid.
+const COLUMNS: usize = (GROUND_WIDTH / OBSTACLE_SIZE) as usize;
+const ROWS: usize = (GROUND_HEIGHT / OBSTACLE_SIZE) as usize;
+
+const CENTRE_CIRCLE_RADIUS: f32 = 80.;
+const MIN_PAIRS: usize = 4;
+const MAX_PAIRS: usize = 8;
+const MAX_ATTEMPTS: usize = 200;
+
+const DIGIT_KEYS: [(KeyCode, char); 10] = [
+    (KeyCode::Digit0, '0'),
+    (KeyCode::Digit1, '1'),
+    (KeyCode::Digit2, '2'),
+    (KeyCode::Digit3, '3'),
+    (KeyCode::Digit4, '4'),
+    (KeyCode::Digit5, '5'),
+    (KeyCode::Digit6, '6'),
+    (KeyCode::Digit7, '7'),
+    (KeyCode::Digit8, '8'),
+    (KeyCode::Digit9, '9'),
+];
+const MAX_SEED_DIGITS: usize = 10;
+
+type Grid = [[bool; ROWS]; COLUMNS];
+
+/// Builds a layout that is mirrored left to right, so both teams face the
+/// same pitch. The same seed and team size always produce the same layout.
+pub(crate) fn generate(seed: u64, players_per_team: usize) -> Vec<Obstacle> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut blocked: Grid = [[false; ROWS]; COLUMNS];
+    let mut obstacles = Vec::new();
+
+    let pairs = rng.gen_range(MIN_PAIRS..=MAX_PAIRS);
+    let mut attempts = 0;
+
+    while obstacles.len() < pairs * 2 && attempts < MAX_ATTEMPTS {
+        attempts += 1;
+
+        let column = rng.gen_range(0..COLUMNS / 2);
+        let row = rng.gen_range(0..ROWS);
+        let mirrored = COLUMNS - 1 - column;
+
+        if blocked[column][row] || is_reserved(column, row, players_per_team) {
+            continue;
+        }
+
+        blocked[column][row] = true;
+        blocked[mirrored][row] = true;
+
+        if !goals_reachable(&blocked) {
+            blocked[column][row] = false;
+            blocked[mirrored][row] = false;
+            continue;
+        }
+
+        let variant = random_variant(&mut rng);
+        obstacles.push(Obstacle {
+            translation: cell_centre(column, row).extend(4.),
+            rotation: 0.,
+            variant: variant.clone(),
+        });
+        obstacles.push(Obstacle {
+            translation: cell_centre(mirrored, row).extend(4.),
+            rotation: 0.,
+            variant,
+        });
+    }
+
+    obstacles
+}
+
+fn random_variant(rng: &mut StdRng) -> ObstacleVariant {
+    match rng.gen_range(0..4) {
+        0 => ObstacleVariant::Bumper { strength: 400. },
+        1 => ObstacleVariant::Breakable {
+            hits: rng.gen_range(2..=4),
+        },
+        _ => ObstacleVariant::Static,
+    }
+}
+
+fn cell_centre(column: usize, row: usize) -> Vec2 {
+    Vec2::new(
+        -GROUND_WIDTH / 2. + (column as f32 + 0.5) * OBSTACLE_SIZE,
+        GROUND_MIDDLE + (row as f32 + 0.5 - ROWS as f32 / 2.) * OBSTACLE_SIZE,
+    )
+}
+
+fn is_goal_mouth(column: usize, row: usize) -> bool {
+    (column == 0 || column == COLUMNS - 1)
+        && (cell_centre(column, row).y - GROUND_MIDDLE).abs() < GOAL_HEIGHT / 2.
+}
+
+/// Cells that must stay empty: the goal mouths, the centre circle and
+/// anywhere a player lines up at kickoff (on either side of the pitch).
+fn is_reserved(column: usize, row: usize, players_per_team: usize) -> bool {
+    let centre = cell_centre(column, row);
+
+    if is_goal_mouth(column, row) {
+        return true;
+    }
+
+    if centre.distance(Vec2::new(0., GROUND_MIDDLE)) < CENTRE_CIRCLE_RADIUS + OBSTACLE_SIZE / 2. {
+        return true;
+    }
+
+    (0..NUM_TEAMS)
+        .flat_map(|team| (0..players_per_team).map(move |player| (team, player)))
+        .map(|(team, player)| starting_position(team, player).truncate())
+        .flat_map(|spawn| [spawn, Vec2::new(-spawn.x, spawn.y)])
+        .any(|spawn| {
+            (centre - spawn)
+                .abs()
+                .cmplt(Vec2::splat(OBSTACLE_SIZE))
+                .all()
+        })
+}
+
+/// Flood fills from the kickoff spot and checks both goal mouths are reached.
+/// Only orthogonal steps count, so the ball never has to squeeze between the
+/// corners of two diagonal obstacles.
+fn goals_reachable(blocked: &Grid) -> bool {
+    let mut visited: Grid = [[false; ROWS]; COLUMNS];
+    let mut queue = VecDeque::from([(COLUMNS / 2, ROWS / 2)]);
+    let mut left = false;
+    let mut right = false;
+
+    while let Some((column, row)) = queue.pop_front() {
+        if visited[column][row] || blocked[column][row] {
+            continue;
+        }
+        visited[column][row] = true;
+
+        if is_goal_mouth(column, row) {
+            if column == 0 {
+                left = true;
+            } else {
+                right = true;
+            }
+        }
+
+        if column > 0 {
+            queue.push_back((column - 1, row));
+        }
+        if column < COLUMNS - 1 {
+            queue.push_back((column + 1, row));
+        }
+        if row > 0 {
+            queue.push_back((column, row - 1));
+        }
+        if row < ROWS - 1 {
+            queue.push_back((column, row + 1));
+        }
+    }
+
+    left && right
+}
+
+/// `P` swaps between the classic and procedural layouts, going procedural
+/// from an arena file too, and `N` rolls a new seed for the procedural one.
+fn switch_layout(
+    keys: Res<ButtonInput<KeyCode>>,
+    entry: Res<SeedEntry>,
+    mut rng: ResMut<LayoutRng>,
+    mut layout: ResMut<ObstacleLayout>,
+) {
+    if entry.0.is_some() {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::KeyP) {
+        *layout = match *layout {
+            ObstacleLayout::Classic | ObstacleLayout::File(_) => ObstacleLayout::Procedural {
+                seed: rng.next_seed(),
+            },
+            ObstacleLayout::Procedural { .. } => ObstacleLayout::Classic,
+        };
+    } else if keys.just_pressed(KeyCode::KeyN) {
+        if let ObstacleLayout::Procedural { .. } = *layout {
+            *layout = ObstacleLayout::Procedural {
+                seed: rng.next_seed(),
+            };
+        }
+    }
+}
+
+/// `Tab` starts typing a seed, `Enter` builds it and `Tab` again cancels.
+fn enter_seed(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut entry: ResMut<SeedEntry>,
+    mut layout: ResMut<ObstacleLayout>,
+) {
+    if keys.just_pressed(KeyCode::Tab) {
+        entry.0 = match entry.0 {
+            Some(_) => None,
+            None => Some(String::new()),
+        };
+        return;
+    }
+
+    let Some(digits) = entry.0.as_mut() else {
+        return;
+    };
+
+    for (key, digit) in DIGIT_KEYS {
+        if keys.just_pressed(key) && digits.len() < MAX_SEED_DIGITS {
+            digits.push(digit);
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Backspace) {
+        digits.pop();
+    }
+
+    if keys.just_pressed(KeyCode::Enter) {
+        if let Ok(seed) = digits.parse() {
+            *layout = ObstacleLayout::Procedural { seed };
+        }
+        entry.0 = None;
+    }
+}
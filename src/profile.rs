@@ -0,0 +1,158 @@
+use std::cmp::Ordering;
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::FullTime,
+    player::{team_input_map, Bindings, Player, PlayerAction, Team, Versus, NUM_TEAMS, TEAM_NAMES},
+    save::SaveData,
+    stats::MatchStats,
+};
+
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                apply_bindings.run_if(resource_changed::<SaveData>),
+                record_profile_result.run_if(on_event::<FullTime>()),
+            ),
+        );
+    }
+}
+
+/// Longest name a profile can have.
+pub const MAX_NAME_LENGTH: usize = 12;
+
+/// Someone who plays on this machine, with their own kit, keys and record.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Profile {
+    pub name: String,
+    /// The team whose kit they play in, and so whose keys are theirs.
+    pub kit: usize,
+    pub bindings: Bindings,
+    #[serde(default)]
+    pub stats: ProfileStats,
+}
+
+impl Profile {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            kit: 0,
+            bindings: Bindings::for_team(0),
+            stats: ProfileStats::default(),
+        }
+    }
+
+    /// Switches to the other kit, taking its default keys if they were
+    /// using the old kit's.
+    pub fn change_kit(&mut self) {
+        let kit = (self.kit + 1) % NUM_TEAMS;
+        if self.bindings == Bindings::for_team(self.kit) {
+            self.bindings = Bindings::for_team(kit);
+        }
+        self.kit = kit;
+    }
+
+    /// The team they play for: their kit's, or the only one on the pitch
+    /// when it isn't versus.
+    pub fn team(&self, versus: Versus) -> usize {
+        if versus.0 {
+            self.kit
+        } else {
+            0
+        }
+    }
+
+    pub fn kit_name(&self) -> &'static str {
+        TEAM_NAMES[self.kit]
+    }
+
+    /// Up to three letters for the leaderboard, from their name.
+    pub fn initials(&self) -> String {
+        self.name
+            .chars()
+            .filter(char::is_ascii_alphabetic)
+            .map(|letter| letter.to_ascii_uppercase())
+            .take(3)
+            .collect()
+    }
+}
+
+/// Every finished match played under a profile, from their side.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct ProfileStats {
+    pub played: u32,
+    pub won: u32,
+    pub drawn: u32,
+    pub lost: u32,
+    pub goals_for: u32,
+    pub goals_against: u32,
+    /// Their records: the most goals their team scored in a match, and the
+    /// biggest margin they won by.
+    pub most_goals: u32,
+    pub biggest_win: u32,
+    /// Drawn matches settled on penalties, which still count as draws above.
+    #[serde(default)]
+    pub shootouts_won: u32,
+    #[serde(default)]
+    pub shootouts_lost: u32,
+}
+
+impl ProfileStats {
+    /// Adds a match their team finished with `goals_for` to `goals_against`.
+    pub fn record(&mut self, goals_for: u32, goals_against: u32) {
+        self.played += 1;
+        match goals_for.cmp(&goals_against) {
+            Ordering::Greater => self.won += 1,
+            Ordering::Equal => self.drawn += 1,
+            Ordering::Less => self.lost += 1,
+        }
+        self.goals_for += goals_for;
+        self.goals_against += goals_against;
+        self.most_goals = self.most_goals.max(goals_for);
+        self.biggest_win = self
+            .biggest_win
+            .max(goals_for.saturating_sub(goals_against));
+    }
+
+    /// Adds the shootout that settled their latest match.
+    pub fn record_shootout(&mut self, won: bool) {
+        if won {
+            self.shootouts_won += 1;
+        } else {
+            self.shootouts_lost += 1;
+        }
+    }
+}
+
+fn apply_bindings(
+    save: Res<SaveData>,
+    versus: Res<Versus>,
+    mut players: Query<(&Team, &mut InputMap<PlayerAction>), With<Player>>,
+) {
+    for (team, mut input_map) in &mut players {
+        *input_map = team_input_map(save.bindings(team.0, *versus), team.0, *versus);
+    }
+}
+
+fn record_profile_result(mut save: ResMut<SaveData>, stats: Res<MatchStats>, versus: Res<Versus>) {
+    let Some(profile) = save.active_profile_mut() else {
+        return;
+    };
+    let their_team = profile.team(*versus);
+    let goals_for = stats.teams[their_team].goals;
+    let goals_against: u32 = stats
+        .teams
+        .iter()
+        .enumerate()
+        .filter(|(team, _)| *team != their_team)
+        .map(|(_, team)| team.goals)
+        .sum();
+    profile.stats.record(goals_for, goals_against);
+}
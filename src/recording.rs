@@ -0,0 +1,611 @@
+use std::{error::Error, fs, path::Path};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::{spawn_goal, Goal, GoalPlacement},
+    ball::{Ball, LastTouch},
+    camera::UI_LAYER,
+    mode::{resume_unless_full_time, GameMode},
+    obstacle::{spawn_obstacle, Obstacle, ObstacleAnchor, ObstacleLayout, ObstacleVariant},
+    player::{
+        movement, player_moves, Player, PlayerMoves, PlayerNumber, PlayerType, Team, NUM_TEAMS,
+    },
+    save::SaveData,
+    simulation::{GameplaySet, SIMULATION_HZ},
+};
+
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchRecorder>()
+            .init_resource::<MatchViewer>()
+            .add_systems(Update, toggle_viewer)
+            .add_systems(
+                Update,
+                (
+                    restart_recording.run_if(resource_changed::<ObstacleLayout>),
+                    save_recording,
+                )
+                    .run_if(in_state(GameMode::Match)),
+            )
+            .add_systems(
+                FixedUpdate,
+                record_tick
+                    .after(player_moves)
+                    .in_set(GameplaySet)
+                    .run_if(in_state(GameMode::Match)),
+            )
+            .add_systems(OnExit(GameMode::Editor), snapshot_after_editing)
+            .add_systems(
+                OnEnter(GameMode::Viewer),
+                (start_viewing, spawn_viewer_banner),
+            )
+            .add_systems(
+                OnExit(GameMode::Viewer),
+                (stop_viewing, resume_unless_full_time, despawn_viewer_banner),
+            )
+            .add_systems(
+                Update,
+                (control_viewer, update_viewer_banner)
+                    .chain()
+                    .run_if(in_state(GameMode::Viewer)),
+            )
+            .add_systems(
+                FixedUpdate,
+                play_recording
+                    .before(movement)
+                    .in_set(GameplaySet)
+                    .run_if(in_state(GameMode::Viewer)),
+            );
+    }
+}
+
+/// Where `F6` saves the match so far and `F7` loads it back to watch.
+pub const RECORDING_PATH: &str = "replays/match.ron";
+
+const RECORDING_VERSION: u32 = 1;
+/// Ticks between snapshots.
+const SNAPSHOT_TICKS: u32 = 2 * SIMULATION_HZ as u32;
+/// How far the arrow keys seek in the viewer.
+const SEEK_TICKS: u32 = 5 * SIMULATION_HZ as u32;
+
+/// What a team's live player was asked to do on a tick.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+struct RecordedInput {
+    direction: Vec2,
+    running: bool,
+}
+
+/// Inputs are only stored when they change, which keeps recordings small.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct InputChange {
+    tick: u32,
+    team: usize,
+    input: Option<RecordedInput>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+struct BallState {
+    translation: Vec3,
+    linvel: Vec2,
+    angvel: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+struct PlayerPosition {
+    team: usize,
+    number: usize,
+    translation: Vec3,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+struct ObstacleState {
+    anchor: Vec3,
+    obstacle: Obstacle,
+}
+
+/// Everything needed to put the match back as it was on `tick`. The
+/// simulation isn't fully deterministic, so playback snaps back to these
+/// whenever it reaches one.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub(crate) struct Snapshot {
+    tick: u32,
+    score: i32,
+    ball: Option<BallState>,
+    players: Vec<PlayerPosition>,
+    /// The team and number of the player who last touched the ball.
+    #[serde(default)]
+    possession: Option<(usize, usize)>,
+    obstacles: Vec<ObstacleState>,
+    goals: Vec<GoalPlacement>,
+}
+
+/// A whole match as the inputs of each team plus periodic snapshots, stored
+/// as RON.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct MatchRecording {
+    version: u32,
+    /// What the arena was built from when recording started.
+    layout: ObstacleLayout,
+    ticks: u32,
+    inputs: Vec<InputChange>,
+    snapshots: Vec<Snapshot>,
+}
+
+impl MatchRecording {
+    pub(crate) fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        let recording: Self = ron::from_str(&contents)?;
+        if recording.version != RECORDING_VERSION {
+            return Err(format!("unsupported recording version {}", recording.version).into());
+        }
+        Ok(recording)
+    }
+
+    pub(crate) fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, ron::ser::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct MatchRecorder {
+    pub(crate) recording: MatchRecording,
+    tick: u32,
+    inputs: [Option<RecordedInput>; NUM_TEAMS],
+    snapshot_due: bool,
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct MatchViewer {
+    pub(crate) recording: MatchRecording,
+    tick: u32,
+    next_input: usize,
+    inputs: [Option<RecordedInput>; NUM_TEAMS],
+    /// The live match and what its arena was built from, put back when the
+    /// viewer closes.
+    pub(crate) resume: Option<(Snapshot, ObstacleLayout)>,
+}
+
+impl MatchViewer {
+    /// Jumps to the last snapshot at or before `target`.
+    fn seek(&mut self, target: u32, state: &mut MatchState) {
+        let snapshots = &self.recording.snapshots;
+        let index = snapshots
+            .partition_point(|snapshot| snapshot.tick <= target)
+            .saturating_sub(1);
+        let Some(snapshot) = snapshots.get(index) else {
+            return;
+        };
+
+        state.restore(snapshot, true);
+        self.tick = snapshot.tick;
+        self.next_input = self
+            .recording
+            .inputs
+            .partition_point(|change| change.tick < snapshot.tick);
+        self.inputs = default();
+        for change in &self.recording.inputs[..self.next_input] {
+            self.inputs[change.team] = change.input;
+        }
+    }
+}
+
+#[derive(Component)]
+struct ViewerBanner;
+
+/// The parts of the world a snapshot covers.
+#[derive(SystemParam)]
+pub(crate) struct MatchState<'w, 's> {
+    commands: Commands<'w, 's>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<ColorMaterial>>,
+    pub(crate) save: ResMut<'w, SaveData>,
+    last_touch: ResMut<'w, LastTouch>,
+    balls: Query<'w, 's, (&'static mut Transform, &'static mut Velocity), With<Ball>>,
+    players: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Team,
+            &'static PlayerNumber,
+            &'static mut Transform,
+        ),
+        (With<Player>, Without<Ball>),
+    >,
+    obstacles: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Transform,
+            &'static ObstacleAnchor,
+            &'static ObstacleVariant,
+        ),
+        (Without<Ball>, Without<Player>),
+    >,
+    goals:
+        Query<'w, 's, (Entity, &'static Transform), (With<Goal>, Without<Ball>, Without<Player>)>,
+}
+
+impl MatchState<'_, '_> {
+    pub(crate) fn capture(&self, tick: u32) -> Snapshot {
+        Snapshot {
+            tick,
+            score: self.save.score,
+            ball: self
+                .balls
+                .iter()
+                .next()
+                .map(|(transform, velocity)| BallState {
+                    translation: transform.translation,
+                    linvel: velocity.linvel,
+                    angvel: velocity.angvel,
+                }),
+            players: self
+                .players
+                .iter()
+                .map(|(_, team, number, transform)| PlayerPosition {
+                    team: team.0,
+                    number: number.0,
+                    translation: transform.translation,
+                })
+                .collect(),
+            possession: self
+                .last_touch
+                .0
+                .and_then(|player| self.players.get(player).ok())
+                .map(|(_, team, number, _)| (team.0, number.0)),
+            obstacles: self
+                .obstacles
+                .iter()
+                .map(|(_, transform, anchor, variant)| ObstacleState {
+                    anchor: anchor.0,
+                    obstacle: Obstacle {
+                        translation: transform.translation,
+                        rotation: rotation_z(transform),
+                        variant: variant.clone(),
+                    },
+                })
+                .collect(),
+            goals: self
+                .goals
+                .iter()
+                .map(|(_, transform)| GoalPlacement {
+                    translation: transform.translation,
+                    rotation: rotation_z(transform),
+                })
+                .collect(),
+        }
+    }
+
+    /// Moves everything back to `snapshot`. Obstacles and goals are only
+    /// rebuilt when `rebuild_arena` is set, since that respawns them.
+    pub(crate) fn restore(&mut self, snapshot: &Snapshot, rebuild_arena: bool) {
+        self.save.score = snapshot.score;
+        self.last_touch.0 = None;
+
+        if let (Some(state), Some((mut transform, mut velocity))) =
+            (&snapshot.ball, self.balls.iter_mut().next())
+        {
+            transform.translation = state.translation;
+            velocity.linvel = state.linvel;
+            velocity.angvel = state.angvel;
+        }
+
+        for (entity, team, number, mut transform) in &mut self.players {
+            if let Some(state) = snapshot
+                .players
+                .iter()
+                .find(|state| state.team == team.0 && state.number == number.0)
+            {
+                transform.translation = state.translation;
+            }
+            if snapshot.possession == Some((team.0, number.0)) {
+                self.last_touch.0 = Some(entity);
+            }
+        }
+
+        if !rebuild_arena {
+            return;
+        }
+
+        for (entity, ..) in &self.obstacles {
+            self.commands.entity(entity).despawn_recursive();
+        }
+        for (entity, _) in &self.goals {
+            self.commands.entity(entity).despawn_recursive();
+        }
+
+        for state in &snapshot.obstacles {
+            let obstacle = &state.obstacle;
+            let entity = spawn_obstacle(
+                &mut self.commands,
+                Obstacle {
+                    translation: state.anchor,
+                    ..obstacle.clone()
+                },
+            );
+            self.commands.entity(entity).insert(
+                Transform::from_translation(obstacle.translation)
+                    .with_rotation(Quat::from_rotation_z(obstacle.rotation)),
+            );
+        }
+        for placement in &snapshot.goals {
+            spawn_goal(
+                &mut self.commands,
+                &mut self.meshes,
+                &mut self.materials,
+                placement,
+            );
+        }
+    }
+}
+
+fn rotation_z(transform: &Transform) -> f32 {
+    transform.rotation.to_euler(EulerRot::XYZ).2
+}
+
+/// A new arena means a new match, so recording starts over.
+fn restart_recording(layout: Res<ObstacleLayout>, mut recorder: ResMut<MatchRecorder>) {
+    *recorder = MatchRecorder {
+        recording: MatchRecording {
+            version: RECORDING_VERSION,
+            layout: layout.clone(),
+            ..default()
+        },
+        snapshot_due: true,
+        ..default()
+    };
+}
+
+fn snapshot_after_editing(mut recorder: ResMut<MatchRecorder>) {
+    recorder.snapshot_due = true;
+}
+
+fn record_tick(
+    mut recorder: ResMut<MatchRecorder>,
+    mut player_moves: EventReader<PlayerMoves>,
+    teams: Query<&Team>,
+    state: MatchState,
+) {
+    let tick = recorder.tick;
+
+    let mut inputs = [None; NUM_TEAMS];
+    for event in player_moves.read() {
+        let Some(team) = event.player.and_then(|player| teams.get(player).ok()) else {
+            continue;
+        };
+        inputs[team.0] = Some(RecordedInput {
+            direction: event.direction.map_or(Vec2::ZERO, |direction| *direction),
+            running: event.running,
+        });
+    }
+
+    for (team, input) in inputs.into_iter().enumerate() {
+        if recorder.inputs[team] != input {
+            recorder.inputs[team] = input;
+            recorder
+                .recording
+                .inputs
+                .push(InputChange { tick, team, input });
+        }
+    }
+
+    if recorder.snapshot_due || tick % SNAPSHOT_TICKS == 0 {
+        recorder.snapshot_due = false;
+        recorder.recording.snapshots.push(state.capture(tick));
+    }
+
+    recorder.tick += 1;
+    recorder.recording.ticks = recorder.tick;
+}
+
+fn save_recording(keys: Res<ButtonInput<KeyCode>>, recorder: Res<MatchRecorder>) {
+    if !keys.just_pressed(KeyCode::F6) {
+        return;
+    }
+
+    match recorder.recording.save(RECORDING_PATH) {
+        Ok(()) => info!("Saved match recording to {RECORDING_PATH}"),
+        Err(error) => warn!("Failed to save match recording to {RECORDING_PATH}: {error}"),
+    }
+}
+
+/// `F7` opens the last saved recording, and closes the viewer again.
+fn toggle_viewer(
+    keys: Res<ButtonInput<KeyCode>>,
+    mode: Res<State<GameMode>>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+    mut viewer: ResMut<MatchViewer>,
+) {
+    if !keys.just_pressed(KeyCode::F7) {
+        return;
+    }
+
+    match mode.get() {
+        GameMode::Match => match MatchRecording::load(RECORDING_PATH) {
+            Ok(recording) => {
+                viewer.recording = recording;
+                next_mode.set(GameMode::Viewer);
+            }
+            Err(error) => warn!("Failed to load match recording from {RECORDING_PATH}: {error}"),
+        },
+        GameMode::Viewer => next_mode.set(GameMode::Match),
+        GameMode::Editor | GameMode::Replay | GameMode::Shootout => {}
+    }
+}
+
+/// Loads the last saved recording if nothing has been, as when the game is
+/// launched straight into the viewer.
+fn start_viewing(
+    mut viewer: ResMut<MatchViewer>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+    mut layout: ResMut<ObstacleLayout>,
+    mut state: MatchState,
+) {
+    if viewer.recording.snapshots.is_empty() {
+        match MatchRecording::load(RECORDING_PATH) {
+            Ok(recording) => viewer.recording = recording,
+            Err(error) => {
+                warn!("Failed to load match recording from {RECORDING_PATH}: {error}");
+                next_mode.set(GameMode::Match);
+                return;
+            }
+        }
+    }
+
+    viewer.resume = Some((state.capture(0), layout.clone()));
+    viewer.seek(0, &mut state);
+    // Seeking rebuilds the recorded obstacles, and changing the layout would
+    // build them again from scratch.
+    *layout.bypass_change_detection() = viewer.recording.layout.clone();
+}
+
+fn stop_viewing(
+    mut viewer: ResMut<MatchViewer>,
+    mut layout: ResMut<ObstacleLayout>,
+    mut state: MatchState,
+) {
+    if let Some((snapshot, live_layout)) = viewer.resume.take() {
+        state.restore(&snapshot, true);
+        *layout.bypass_change_detection() = live_layout;
+    }
+}
+
+/// `Space` pauses, the left and right arrows seek and `Home` goes back to
+/// the start.
+fn control_viewer(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut viewer: ResMut<MatchViewer>,
+    mut state: MatchState,
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        if time.is_paused() {
+            time.unpause();
+        } else {
+            time.pause();
+        }
+        rapier_config.physics_pipeline_active = !time.is_paused();
+    }
+
+    let target = if keys.just_pressed(KeyCode::ArrowLeft) {
+        Some(viewer.tick.saturating_sub(SEEK_TICKS))
+    } else if keys.just_pressed(KeyCode::ArrowRight) {
+        Some(viewer.tick + SEEK_TICKS)
+    } else if keys.just_pressed(KeyCode::Home) {
+        Some(0)
+    } else {
+        None
+    };
+
+    if let Some(target) = target {
+        viewer.seek(target, &mut state);
+    }
+}
+
+/// Feeds the recorded inputs to the live players, one fixed tick at a time.
+fn play_recording(
+    mut viewer: ResMut<MatchViewer>,
+    mut state: MatchState,
+    mut player_moves: EventWriter<PlayerMoves>,
+    players: Query<(Entity, &Team, &PlayerType)>,
+) {
+    let tick = viewer.tick;
+    if tick >= viewer.recording.ticks {
+        return;
+    }
+
+    if let Ok(index) = viewer
+        .recording
+        .snapshots
+        .binary_search_by_key(&tick, |snapshot| snapshot.tick)
+    {
+        state.restore(&viewer.recording.snapshots[index], false);
+    }
+
+    while let Some(change) = viewer.recording.inputs.get(viewer.next_input) {
+        if change.tick > tick {
+            break;
+        }
+        let (team, input) = (change.team, change.input);
+        viewer.inputs[team] = input;
+        viewer.next_input += 1;
+    }
+
+    for (entity, team, player_type) in &players {
+        if *player_type != PlayerType::Live {
+            continue;
+        }
+        if let Some(input) = viewer.inputs.get(team.0).copied().flatten() {
+            player_moves.send(PlayerMoves {
+                player: Some(entity),
+                direction: Direction2d::new(input.direction).ok(),
+                running: input.running,
+            });
+        }
+    }
+
+    viewer.tick += 1;
+}
+
+fn spawn_viewer_banner(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/More 15 Basic.ttf");
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                color: Color::WHITE,
+                font_size: 16.0,
+                font,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(4.),
+            left: Val::Px(4.),
+            ..default()
+        }),
+        ViewerBanner,
+        UI_LAYER,
+    ));
+}
+
+fn update_viewer_banner(
+    time: Res<Time<Virtual>>,
+    fixed_time: Res<Time<Fixed>>,
+    viewer: Res<MatchViewer>,
+    mut query: Query<&mut Text, With<ViewerBanner>>,
+) {
+    let seconds = |ticks: u32| {
+        let seconds = (ticks as f32 * fixed_time.timestep().as_secs_f32()) as u32;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    };
+
+    for mut text in &mut query {
+        text.sections[0].value = format!(
+            "VIEWER {} {} / {}  SPACE pause  <- -> seek  HOME start  F7 exit",
+            if time.is_paused() { "||" } else { ">>" },
+            seconds(viewer.tick),
+            seconds(viewer.recording.ticks),
+        );
+    }
+}
+
+fn despawn_viewer_banner(mut commands: Commands, query: Query<Entity, With<ViewerBanner>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,424 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    arena::GoalEvent,
+    ball::Ball,
+    camera::{apply_camera_rig, CameraRig, MainCamera, UI_LAYER},
+    clock::MatchClock,
+    mode::{pause_simulation, resume_unless_full_time, GameMode},
+    obstacle::ObstacleVariant,
+    player::Player,
+    simulation::interpolate_transforms,
+};
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayBuffer>()
+            .init_resource::<ReplayPlayback>()
+            .add_systems(
+                Update,
+                (queue_replay, start_replay).run_if(in_state(GameMode::Match)),
+            )
+            .add_systems(
+                OnEnter(GameMode::Replay),
+                (pause_simulation, begin_playback, spawn_replay_banner),
+            )
+            .add_systems(
+                OnExit(GameMode::Replay),
+                (resume_unless_full_time, end_playback, despawn_replay_banner),
+            )
+            .add_systems(
+                Update,
+                (control_playback, advance_playback, update_replay_banner)
+                    .chain()
+                    .before(apply_camera_rig)
+                    .run_if(in_state(GameMode::Replay)),
+            )
+            .add_systems(
+                PostUpdate,
+                show_replay_frame
+                    .before(interpolate_transforms)
+                    .run_if(in_state(GameMode::Replay)),
+            )
+            .add_systems(Last, record_frame.run_if(in_state(GameMode::Match)));
+    }
+}
+
+/// How much of the match is kept for replays.
+const REPLAY_SECONDS: f32 = 5.;
+/// Real time between a goal and its replay, so the celebration can play out.
+const REPLAY_DELAY: f32 = 1.5;
+const SPEEDS: [f32; 3] = [0.25, 0.5, 1.];
+const DEFAULT_SPEED: usize = 1;
+
+/// What a recorded pose belongs to. The ball is despawned when a goal goes
+/// in, so it is tracked by role and played back on whichever ball exists.
+#[derive(Clone, Copy, PartialEq)]
+enum Subject {
+    Ball,
+    Entity(Entity),
+}
+
+#[derive(Clone)]
+struct Pose {
+    subject: Subject,
+    transform: Transform,
+    atlas_index: Option<usize>,
+}
+
+struct ReplayFrame {
+    /// Virtual seconds since startup.
+    time: f32,
+    camera_focus: Vec2,
+    camera_zoom: f32,
+    poses: Vec<Pose>,
+}
+
+/// The last `REPLAY_SECONDS` of play, oldest first.
+#[derive(Resource)]
+pub struct ReplayBuffer {
+    frames: VecDeque<ReplayFrame>,
+    recording: bool,
+}
+
+impl Default for ReplayBuffer {
+    fn default() -> Self {
+        Self {
+            frames: VecDeque::new(),
+            recording: true,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub(crate) struct ReplayPlayback {
+    /// Counts down from a goal to the start of its replay.
+    countdown: Option<Timer>,
+    /// The recorded time being shown.
+    cursor: f32,
+    speed: usize,
+    rewinding: bool,
+    /// Where everything was when the replay started, put back afterwards.
+    snapshot: Vec<Pose>,
+}
+
+impl Default for ReplayPlayback {
+    fn default() -> Self {
+        Self {
+            countdown: None,
+            cursor: 0.,
+            speed: DEFAULT_SPEED,
+            rewinding: false,
+            snapshot: Vec::new(),
+        }
+    }
+}
+
+#[derive(Component)]
+struct ReplayBanner;
+
+type Recorded = Or<(With<Ball>, With<Player>, With<ObstacleVariant>)>;
+type RecordedQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        Option<&'static TextureAtlas>,
+        Has<Ball>,
+    ),
+    Recorded,
+>;
+type RecordedQueryMut<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Transform,
+        Option<&'static mut TextureAtlas>,
+        Has<Ball>,
+    ),
+    Recorded,
+>;
+
+fn poses(query: &RecordedQuery) -> Vec<Pose> {
+    query
+        .iter()
+        .map(|(entity, transform, atlas, is_ball)| Pose {
+            subject: if is_ball {
+                Subject::Ball
+            } else {
+                Subject::Entity(entity)
+            },
+            transform: *transform,
+            atlas_index: atlas.map(|atlas| atlas.index),
+        })
+        .collect()
+}
+
+fn record_frame(
+    time: Res<Time<Virtual>>,
+    mut buffer: ResMut<ReplayBuffer>,
+    recorded: RecordedQuery,
+    rigs: Query<&CameraRig, With<MainCamera>>,
+) {
+    if !buffer.recording || time.is_paused() {
+        return;
+    }
+
+    let now = time.elapsed_seconds();
+    let (camera_focus, camera_zoom) = rigs
+        .get_single()
+        .map_or((Vec2::ZERO, 1.), |rig| (rig.focus, rig.zoom));
+
+    buffer.frames.push_back(ReplayFrame {
+        time: now,
+        camera_focus,
+        camera_zoom,
+        poses: poses(&recorded),
+    });
+
+    while buffer
+        .frames
+        .front()
+        .is_some_and(|frame| frame.time < now - REPLAY_SECONDS)
+    {
+        buffer.frames.pop_front();
+    }
+}
+
+/// Stops recording as soon as a goal goes in, so the replay ends on it.
+fn queue_replay(
+    clock: Res<MatchClock>,
+    mut goal_events: EventReader<GoalEvent>,
+    mut buffer: ResMut<ReplayBuffer>,
+    mut playback: ResMut<ReplayPlayback>,
+) {
+    let scored = goal_events.read().count() > 0;
+    if !scored || playback.countdown.is_some() || clock.is_full_time() {
+        return;
+    }
+
+    buffer.recording = false;
+    playback.countdown = Some(Timer::from_seconds(REPLAY_DELAY, TimerMode::Once));
+}
+
+/// A goal in the last moments is never replayed: the match is frozen on its
+/// result at full time, and recording starts afresh for the next one.
+fn start_replay(
+    time: Res<Time<Real>>,
+    clock: Res<MatchClock>,
+    mut buffer: ResMut<ReplayBuffer>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+) {
+    if clock.is_full_time() && playback.countdown.take().is_some() {
+        buffer.frames.clear();
+        buffer.recording = true;
+    }
+    let Some(countdown) = playback.countdown.as_mut() else {
+        return;
+    };
+
+    if countdown.tick(time.delta()).finished() {
+        playback.countdown = None;
+        if buffer.frames.is_empty() {
+            return;
+        }
+        next_mode.set(GameMode::Replay);
+    }
+}
+
+fn begin_playback(
+    buffer: Res<ReplayBuffer>,
+    mut playback: ResMut<ReplayPlayback>,
+    recorded: RecordedQuery,
+) {
+    playback.cursor = buffer.frames.front().map_or(0., |frame| frame.time);
+    playback.speed = DEFAULT_SPEED;
+    playback.rewinding = false;
+    playback.snapshot = poses(&recorded);
+}
+
+/// Puts everything back where it was when the replay started, so the live
+/// match can be read mid-replay, as when the game is closed during one.
+pub(crate) fn restore_live_poses(
+    mut playback: ResMut<ReplayPlayback>,
+    mut recorded: RecordedQueryMut,
+) {
+    for pose in playback.snapshot.drain(..) {
+        apply_pose(&mut recorded, &pose);
+    }
+}
+
+/// Puts everything back for kickoff and starts recording afresh.
+fn end_playback(
+    mut buffer: ResMut<ReplayBuffer>,
+    playback: ResMut<ReplayPlayback>,
+    recorded: RecordedQueryMut,
+) {
+    restore_live_poses(playback, recorded);
+
+    buffer.frames.clear();
+    buffer.recording = true;
+}
+
+/// `,` and `.` change the speed, `R` plays backwards and `Space` skips to
+/// the kickoff.
+fn control_playback(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+) {
+    if keys.just_pressed(KeyCode::Comma) {
+        playback.speed = playback.speed.saturating_sub(1);
+    }
+    if keys.just_pressed(KeyCode::Period) {
+        playback.speed = (playback.speed + 1).min(SPEEDS.len() - 1);
+    }
+    if keys.just_pressed(KeyCode::KeyR) {
+        playback.rewinding = !playback.rewinding;
+    }
+    if keys.just_pressed(KeyCode::Space) {
+        next_mode.set(GameMode::Match);
+    }
+}
+
+/// Moves the cursor on in real time, since virtual time is paused, and
+/// points the camera where it was looking at that moment.
+fn advance_playback(
+    time: Res<Time<Real>>,
+    buffer: Res<ReplayBuffer>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+    mut rigs: Query<&mut CameraRig, With<MainCamera>>,
+) {
+    let (Some(first), Some(last)) = (buffer.frames.front(), buffer.frames.back()) else {
+        next_mode.set(GameMode::Match);
+        return;
+    };
+
+    let step = time.delta_seconds() * SPEEDS[playback.speed];
+    if playback.rewinding {
+        playback.cursor = (playback.cursor - step).max(first.time);
+    } else {
+        playback.cursor += step;
+        if playback.cursor >= last.time {
+            next_mode.set(GameMode::Match);
+        }
+    }
+
+    let (from, to, blend) = frames_around(&buffer.frames, playback.cursor);
+    if let Ok(mut rig) = rigs.get_single_mut() {
+        rig.focus = from.camera_focus.lerp(to.camera_focus, blend);
+        rig.zoom = from.camera_zoom + (to.camera_zoom - from.camera_zoom) * blend;
+    }
+}
+
+/// The recorded frames either side of `time` and how far between them it is.
+fn frames_around(frames: &VecDeque<ReplayFrame>, time: f32) -> (&ReplayFrame, &ReplayFrame, f32) {
+    let next = frames.partition_point(|frame| frame.time <= time);
+    let from = &frames[next.saturating_sub(1)];
+    let to = frames.get(next).unwrap_or(from);
+
+    let span = to.time - from.time;
+    let blend = if span > 0. {
+        ((time - from.time) / span).clamp(0., 1.)
+    } else {
+        0.
+    };
+
+    (from, to, blend)
+}
+
+/// Runs after the animation systems so the recorded atlas frames win, and
+/// before interpolation so the replayed transforms are taken as jumps.
+fn show_replay_frame(
+    buffer: Res<ReplayBuffer>,
+    playback: Res<ReplayPlayback>,
+    mut recorded: RecordedQueryMut,
+) {
+    if buffer.frames.is_empty() {
+        return;
+    }
+
+    let (from, to, blend) = frames_around(&buffer.frames, playback.cursor);
+    for pose in &from.poses {
+        let mut pose = pose.clone();
+        if let Some(next) = to.poses.iter().find(|next| next.subject == pose.subject) {
+            pose.transform.translation = pose
+                .transform
+                .translation
+                .lerp(next.transform.translation, blend);
+            pose.transform.rotation = pose
+                .transform
+                .rotation
+                .slerp(next.transform.rotation, blend);
+            pose.transform.scale = pose.transform.scale.lerp(next.transform.scale, blend);
+        }
+        apply_pose(&mut recorded, &pose);
+    }
+}
+
+fn apply_pose(recorded: &mut RecordedQueryMut, pose: &Pose) {
+    for (entity, mut transform, atlas, is_ball) in recorded.iter_mut() {
+        let matches = match pose.subject {
+            Subject::Ball => is_ball,
+            Subject::Entity(subject) => subject == entity,
+        };
+        if !matches {
+            continue;
+        }
+
+        *transform = pose.transform;
+        if let (Some(mut atlas), Some(index)) = (atlas, pose.atlas_index) {
+            atlas.index = index;
+        }
+    }
+}
+
+fn spawn_replay_banner(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/More 15 Basic.ttf");
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                color: Color::WHITE,
+                font_size: 16.0,
+                font,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(4.),
+            left: Val::Px(4.),
+            ..default()
+        }),
+        ReplayBanner,
+        UI_LAYER,
+    ));
+}
+
+fn update_replay_banner(
+    playback: Res<ReplayPlayback>,
+    mut query: Query<&mut Text, With<ReplayBanner>>,
+) {
+    for mut text in &mut query {
+        text.sections[0].value = format!(
+            "REPLAY {} x{}  , . speed  R rewind  SPACE skip",
+            if playback.rewinding { "<<" } else { ">>" },
+            SPEEDS[playback.speed],
+        );
+    }
+}
+
+fn despawn_replay_banner(mut commands: Commands, query: Query<Entity, With<ReplayBanner>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
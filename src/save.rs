@@ -0,0 +1,198 @@
+use bevy::prelude::*;
+use bevy_pkv::{GetError, PkvStore};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    leaderboard::{HighScores, MatchResult},
+    mode::GameMode,
+    obstacle::ObstacleLayout,
+    player::{Bindings, Versus, NUM_TEAMS},
+    profile::Profile,
+    suspend::SuspendedMatch,
+};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveData>()
+            .init_resource::<KeepStoredSave>()
+            .add_systems(PreStartup, load_save_data)
+            .add_systems(
+                Last,
+                write_save_data.run_if(
+                    resource_changed::<SaveData>.and_then(|keep: Res<KeepStoredSave>| !keep.0),
+                ),
+            );
+    }
+}
+
+/// Bumped whenever `SaveData` changes in a way old saves can't just be read
+/// as, with a migration added to `MIGRATIONS` for it.
+pub const SAVE_VERSION: u32 = 1;
+
+/// The `PkvStore` key everything is saved under.
+pub const SAVE_KEY: &str = "save";
+
+/// Everything kept between sessions. Systems read and change this rather
+/// than the `PkvStore`, and it's written back at the end of any frame it
+/// changed in.
+#[derive(Resource, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SaveData {
+    pub version: u32,
+    /// Goals in the match being played, by either team.
+    pub score: i32,
+    pub high_scores: HighScores,
+    pub profiles: Vec<Profile>,
+    /// Index into `profiles`. Nobody's results are kept without one.
+    pub active_profile: Option<usize>,
+    /// The match the game was closed in the middle of, if it was.
+    pub suspended: Option<SuspendedMatch>,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            score: 0,
+            high_scores: HighScores::default(),
+            profiles: Vec::new(),
+            active_profile: None,
+            suspended: None,
+        }
+    }
+}
+
+/// Set when the stored save couldn't be used. The game carries on with a new
+/// one but doesn't write it, so the old save is still there for a newer game,
+/// or someone, to recover.
+#[derive(Resource, Default)]
+struct KeepStoredSave(bool);
+
+/// `MIGRATIONS[n]` brings a save from version `n` up to `n + 1`. Version 0
+/// is the separate keys kept before there was a `SaveData`.
+const MIGRATIONS: [fn(&mut SaveData, &PkvStore); SAVE_VERSION as usize] = [from_legacy_keys];
+
+impl SaveData {
+    /// Reads the save and brings it up to date. Anything that can't be read
+    /// is logged and replaced with defaults, so a bad save never stops the
+    /// game from starting.
+    pub fn load(pkv: &PkvStore) -> Self {
+        Self::read(pkv).unwrap_or_default()
+    }
+
+    /// Like [`SaveData::load`], but `None` when the stored save can't be
+    /// used.
+    fn read(pkv: &PkvStore) -> Option<Self> {
+        let mut save = match pkv.get::<SaveData>(SAVE_KEY) {
+            Ok(save) => save,
+            Err(GetError::NotFound) => SaveData {
+                version: 0,
+                ..default()
+            },
+            Err(error) => {
+                warn!("Failed to read the save, starting a new one: {error}");
+                return None;
+            }
+        };
+
+        if save.version > SAVE_VERSION {
+            warn!(
+                "The save is from a newer version of the game ({} > {SAVE_VERSION}), starting a new one",
+                save.version
+            );
+            return None;
+        }
+        for migration in &MIGRATIONS[save.version as usize..] {
+            migration(&mut save, pkv);
+            save.version += 1;
+            info!("Migrated the save to version {}", save.version);
+        }
+        Some(save)
+    }
+
+    /// Logs rather than panics if the write fails. The game carries on with
+    /// what's in memory.
+    pub fn write(&self, pkv: &mut PkvStore) {
+        if let Err(error) = pkv.set(SAVE_KEY, self) {
+            warn!("Failed to write the save: {error}");
+        }
+    }
+}
+
+impl SaveData {
+    pub fn active_profile(&self) -> Option<&Profile> {
+        self.active_profile
+            .and_then(|index| self.profiles.get(index))
+    }
+
+    pub fn active_profile_mut(&mut self) -> Option<&mut Profile> {
+        self.active_profile
+            .and_then(|index| self.profiles.get_mut(index))
+    }
+
+    /// The keys `team` plays with: the active profile's if it's their team.
+    pub fn bindings(&self, team: usize, versus: Versus) -> Bindings {
+        match self.active_profile() {
+            Some(profile) if profile.team(versus) == team => profile.bindings,
+            _ => Bindings::for_team(team),
+        }
+    }
+
+    /// Credits `result` to the active profile if their team won it, or drew.
+    /// `goals` is what each team scored.
+    pub(crate) fn attribute(
+        &self,
+        result: &mut MatchResult,
+        goals: [u32; NUM_TEAMS],
+        versus: Versus,
+    ) {
+        let Some(profile) = self.active_profile() else {
+            return;
+        };
+        if goals[profile.team(versus)] == result.goals {
+            result.profile = Some(profile.name.clone());
+        }
+    }
+}
+
+/// Picks up the bare `score` and `high_score` numbers. The old high score
+/// doesn't say who set it or when, so it goes on the table as `???` with no
+/// date.
+fn from_legacy_keys(save: &mut SaveData, pkv: &PkvStore) {
+    save.score = pkv.get::<i32>("score").unwrap_or(0);
+
+    let high_score = pkv.get::<i32>("high_score").unwrap_or(0);
+    if high_score > 0 {
+        save.high_scores.table.push(MatchResult {
+            initials: "???".to_string(),
+            goals: high_score as u32,
+            margin: 0,
+            date: 0,
+            arena: ObstacleLayout::Classic,
+            mode: GameMode::Match,
+            profile: None,
+            penalties: None,
+        });
+    }
+}
+
+fn load_save_data(
+    mut save: ResMut<SaveData>,
+    mut keep: ResMut<KeepStoredSave>,
+    pkv: Res<PkvStore>,
+) {
+    match SaveData::read(&pkv) {
+        Some(stored) => *save = stored,
+        None => {
+            warn!("Nothing will be saved this session, to keep the stored save as it is");
+            *save = SaveData::default();
+            keep.0 = true;
+        }
+    }
+}
+
+pub(crate) fn write_save_data(save: Res<SaveData>, mut pkv: ResMut<PkvStore>) {
+    save.write(&mut pkv);
+}
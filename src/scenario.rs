@@ -0,0 +1,277 @@
+use std::{error::Error, fmt, fs, path::Path};
+
+use bevy::prelude::*;
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ball::LastTouch,
+    config::LaunchConfig,
+    headless::HeadlessApp,
+    player::{Player, PlayerAction, PlayerNumber, Team},
+    save::SaveData,
+};
+
+/// A scripted bit of play: who presses what and when, and what should have
+/// happened by a given tick. Scenarios are stored as RON and run headlessly,
+/// so how the game feels can be pinned down in tests.
+///
+/// Ticks count from when the scenario starts, at `SIMULATION_HZ` a second.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Scenario {
+    pub name: String,
+    /// Start from an empty pitch rather than the generated layout.
+    #[serde(default)]
+    pub clear_obstacles: bool,
+    /// Put the second team on the pitch too.
+    #[serde(default)]
+    pub versus: bool,
+    /// Where the ball starts and how fast it is moving, if not at kickoff.
+    #[serde(default)]
+    pub ball: Option<BallStart>,
+    #[serde(default)]
+    pub presses: Vec<Press>,
+    pub expect: Vec<Expectation>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BallStart {
+    pub translation: Vec2,
+    #[serde(default)]
+    pub linvel: Vec2,
+}
+
+/// Picks out a player by team and line-up number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PlayerRef {
+    pub team: usize,
+    pub number: usize,
+}
+
+impl fmt::Display for PlayerRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "team {} player {}", self.team, self.number)
+    }
+}
+
+/// Holds `action` down for `player` from tick `from` up to, but not
+/// including, tick `until`. Only live players act on their input.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Press {
+    pub player: PlayerRef,
+    pub action: PlayerAction,
+    pub from: u32,
+    pub until: u32,
+}
+
+impl Press {
+    fn held_on(&self, tick: u32) -> bool {
+        (self.from..self.until).contains(&tick)
+    }
+}
+
+/// A rectangle on the pitch, corners included.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Region {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Region {
+    fn contains(&self, point: Vec2) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+}
+
+/// What should be true once `tick` ticks have run.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Expectation {
+    pub tick: u32,
+    pub check: Check,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum Check {
+    BallIn(Region),
+    PlayerIn(PlayerRef, Region),
+    Score(i32),
+    /// Who last touched the ball, or `None` if nobody has since kickoff.
+    Possession(Option<PlayerRef>),
+}
+
+impl Check {
+    fn verify(&self, game: &mut HeadlessApp) -> Result<(), String> {
+        match self {
+            Check::BallIn(region) => {
+                let ball = game.ball();
+                let position = translation(game.world(), ball);
+                if region.contains(position) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "expected the ball in {region:?}, found it at {position}"
+                    ))
+                }
+            }
+            Check::PlayerIn(player, region) => {
+                let entity = find_player(game.world(), *player)
+                    .ok_or_else(|| format!("there is no {player}"))?;
+                let position = translation(game.world(), entity);
+                if region.contains(position) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "expected {player} in {region:?}, found them at {position}"
+                    ))
+                }
+            }
+            Check::Score(expected) => {
+                let score = game.world().resource::<SaveData>().score;
+                if score == *expected {
+                    Ok(())
+                } else {
+                    Err(format!("expected a score of {expected}, found {score}"))
+                }
+            }
+            Check::Possession(expected) => {
+                let world = game.world();
+                let last_touch = world.resource::<LastTouch>().0;
+                let found = last_touch.and_then(|entity| {
+                    let team = world.get::<Team>(entity)?;
+                    let number = world.get::<PlayerNumber>(entity)?;
+                    Some(PlayerRef {
+                        team: team.0,
+                        number: number.0,
+                    })
+                });
+                if found == *expected {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "expected the ball last touched by {}, found {}",
+                        describe(*expected),
+                        describe(found)
+                    ))
+                }
+            }
+        }
+    }
+}
+
+fn describe(player: Option<PlayerRef>) -> String {
+    player.map_or("nobody".into(), |player| player.to_string())
+}
+
+fn translation(world: &World, entity: Entity) -> Vec2 {
+    world
+        .get::<Transform>(entity)
+        .map_or(Vec2::NAN, |transform| transform.translation.truncate())
+}
+
+fn find_player(world: &mut World, player: PlayerRef) -> Option<Entity> {
+    world
+        .query_filtered::<(Entity, &Team, &PlayerNumber), With<Player>>()
+        .iter(world)
+        .find(|(_, team, number)| team.0 == player.team && number.0 == player.number)
+        .map(|(entity, ..)| entity)
+}
+
+/// The failed expectations of a scenario run.
+#[derive(Debug)]
+pub struct ScenarioFailure {
+    pub scenario: String,
+    pub failures: Vec<(u32, String)>,
+}
+
+impl fmt::Display for ScenarioFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "scenario \"{}\" failed:", self.scenario)?;
+        for (tick, failure) in &self.failures {
+            write!(f, "\n  tick {tick}: {failure}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ScenarioFailure {}
+
+impl Scenario {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        Ok(ron::from_str(&contents)?)
+    }
+
+    /// Plays the scenario out in a fresh headless game, checking every
+    /// expectation on its tick.
+    pub fn run(&self) -> Result<(), ScenarioFailure> {
+        let mut game = HeadlessApp::with_config(&LaunchConfig {
+            versus: self.versus,
+            ..LaunchConfig::default()
+        });
+        if self.clear_obstacles {
+            game.clear_obstacles();
+        }
+        if let Some(ball) = &self.ball {
+            game.kick_ball(ball.translation, ball.linvel);
+        }
+        game.app
+            .insert_resource(ScriptedInput {
+                presses: self.presses.clone(),
+                tick: 0,
+            })
+            .add_systems(
+                PreUpdate,
+                press_scripted_actions.in_set(InputManagerSystem::ManualControl),
+            );
+
+        let last_tick = self.expect.iter().map(|expectation| expectation.tick).max();
+        let mut failures = Vec::new();
+        for tick in 0..=last_tick.unwrap_or(0) {
+            if tick > 0 {
+                game.step(1);
+            }
+            for expectation in self
+                .expect
+                .iter()
+                .filter(|expectation| expectation.tick == tick)
+            {
+                if let Err(failure) = expectation.check.verify(&mut game) {
+                    failures.push((tick, failure));
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(ScenarioFailure {
+                scenario: self.name.clone(),
+                failures,
+            })
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ScriptedInput {
+    presses: Vec<Press>,
+    /// The tick about to run.
+    tick: u32,
+}
+
+/// Runs after leafwing has read the real input, which is never pressed in a
+/// headless game, so the scripted presses are the only ones that count.
+fn press_scripted_actions(
+    mut script: ResMut<ScriptedInput>,
+    mut players: Query<(&Team, &PlayerNumber, &mut ActionState<PlayerAction>)>,
+) {
+    let tick = script.tick;
+    for press in script.presses.iter().filter(|press| press.held_on(tick)) {
+        for (team, number, mut action_state) in &mut players {
+            if team.0 == press.player.team && number.0 == press.player.number {
+                action_state.press(&press.action);
+            }
+        }
+    }
+    script.tick += 1;
+}
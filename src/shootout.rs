@@ -0,0 +1,598 @@
+use bevy::{prelude::*, sprite::Anchor};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    arena::GoalEvent,
+    ball::{Ball, LastTouch},
+    camera::UI_LAYER,
+    clock::MatchClock,
+    constants::*,
+    leaderboard::InitialsEntry,
+    mode::{pause_simulation, resume_simulation, GameMode},
+    obstacle::{spawn_obstacles, ObstacleVariant},
+    player::{
+        starting_position, Marker, Player, PlayerNumber, Team, Versus, NUM_TEAMS, TEAM_COLORS,
+        TEAM_NAMES,
+    },
+    save::SaveData,
+    simulation::{GameplaySet, Interpolated, SIMULATION_HZ},
+    stats::{start_new_match, FullTimeScreen, MatchStats},
+};
+
+pub struct ShootoutPlugin;
+
+impl Plugin for ShootoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Shootout>()
+            .add_systems(Update, start_tie_breaker.run_if(in_state(GameMode::Match)))
+            .add_systems(
+                OnEnter(GameMode::Shootout),
+                (resume_simulation, set_up_shootout),
+            )
+            .add_systems(
+                OnExit(GameMode::Shootout),
+                (
+                    record_tie_breaker.run_if(|shootout: Res<Shootout>| shootout.tie_breaker),
+                    tear_down_shootout,
+                    start_new_match.run_if(|shootout: Res<Shootout>| !shootout.tie_breaker),
+                    pause_simulation.run_if(|clock: Res<MatchClock>| clock.is_full_time()),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                FixedUpdate,
+                (place_ball, choose_dive, take_kick, dive, judge_kick)
+                    .chain()
+                    .in_set(GameplaySet)
+                    .run_if(in_state(GameMode::Shootout)),
+            )
+            .add_systems(
+                Update,
+                hide_new_obstacles
+                    .after(spawn_obstacles)
+                    .run_if(in_state(GameMode::Shootout)),
+            )
+            .add_systems(
+                Update,
+                (update_scoreboard, update_aim_arrow, leave_shootout)
+                    .run_if(in_state(GameMode::Shootout)),
+            );
+    }
+}
+
+/// Kicks each team takes before it goes to sudden death.
+pub const ROUNDS: usize = 5;
+
+const GOAL_LINE: f32 = VIRTUAL_WIDTH / 2.;
+/// Every kick is taken from here at the goal on the right.
+pub const PENALTY_SPOT: Vec2 = Vec2::new(GOAL_LINE - 120., GROUND_MIDDLE);
+const KEEPER_START: Vec2 = Vec2::new(GOAL_LINE - 30., GROUND_MIDDLE);
+const KEEPER_SIZE: Vec2 = Vec2::new(10., 40.);
+/// How far along the line the keeper gets, and how quickly.
+const DIVE_REACH: f32 = 60.;
+const DIVE_SPEED: f32 = 240.;
+
+/// Radians either side of straight at the goal. The widest shots miss.
+const MAX_AIM: f32 = 0.75;
+const AIM_SPEED: f32 = 1.;
+/// Power builds from nothing to full in a second, and a full-power shot
+/// goes off on its own.
+const POWER_RATE: f32 = 1.;
+const MIN_SHOT_SPEED: f32 = 200.;
+const MAX_SHOT_SPEED: f32 = 450.;
+
+/// A kick that hasn't gone in by then has missed.
+const FLIGHT_TICKS: u32 = (1.5 * SIMULATION_HZ) as u32;
+/// How long the result is shown before the next kick.
+const RESULT_TICKS: u32 = SIMULATION_HZ as u32;
+
+const ARROW_LENGTH: f32 = 60.;
+
+/// The kicks taken so far, and who's won.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShootoutScore {
+    /// Each team's kicks in order, `true` for a goal.
+    pub kicks: [Vec<bool>; NUM_TEAMS],
+}
+
+impl ShootoutScore {
+    /// Blue kicks first, then the teams take turns.
+    pub fn kicking_team(&self) -> usize {
+        if self.kicks[0].len() > self.kicks[1].len() {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Adds the kick just taken by `kicking_team`.
+    pub fn record(&mut self, goal: bool) {
+        let team = self.kicking_team();
+        self.kicks[team].push(goal);
+    }
+
+    pub fn goals(&self) -> [usize; NUM_TEAMS] {
+        self.kicks
+            .each_ref()
+            .map(|kicks| kicks.iter().filter(|&&goal| goal).count())
+    }
+
+    /// Over the first `ROUNDS` kicks a team has won once the other can't
+    /// catch up with the kicks it has left. After that it's sudden death:
+    /// the first round one team scores and the other doesn't.
+    pub fn winner(&self) -> Option<usize> {
+        let goals = self.goals();
+        let taken = self.kicks.each_ref().map(Vec::len);
+
+        if taken.iter().all(|&taken| taken <= ROUNDS) {
+            return (0..NUM_TEAMS).find(|&team| {
+                let other = 1 - team;
+                goals[team] > goals[other] + (ROUNDS - taken[other])
+            });
+        }
+        if taken[0] != taken[1] || goals[0] == goals[1] {
+            return None;
+        }
+        Some(if goals[0] > goals[1] { 0 } else { 1 })
+    }
+}
+
+/// The shootout in progress.
+#[derive(Resource, Default)]
+pub struct Shootout {
+    pub score: ShootoutScore,
+    /// Whether it's settling a drawn match, which is still there to go back
+    /// to afterwards, rather than played on its own.
+    pub tie_breaker: bool,
+    phase: KickPhase,
+    /// Radians from straight at the goal, positive being up.
+    aim: f32,
+    /// From 0 to 1.
+    power: f32,
+    dive: Dive,
+}
+
+impl Shootout {
+    pub fn new(tie_breaker: bool) -> Self {
+        Self {
+            tie_breaker,
+            ..default()
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.phase == KickPhase::Finished
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum KickPhase {
+    /// Waiting to put the ball on the spot, once there's a ball.
+    #[default]
+    Setup,
+    /// The kicker is lining up the shot and the keeper is picking a side.
+    Aiming,
+    InFlight {
+        ticks: u32,
+    },
+    Result {
+        goal: bool,
+        ticks: u32,
+    },
+    Finished,
+}
+
+/// Which way the keeper goes when the ball is kicked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Dive {
+    #[default]
+    Stay,
+    Up,
+    Down,
+}
+
+#[derive(Component)]
+struct Keeper;
+
+#[derive(Component)]
+struct AimArrow;
+
+#[derive(Component)]
+struct Scoreboard;
+
+/// `S` at full time, when the match was drawn and hasn't been settled yet.
+fn start_tie_breaker(
+    keys: Res<ButtonInput<KeyCode>>,
+    clock: Res<MatchClock>,
+    stats: Res<MatchStats>,
+    entry: Res<InitialsEntry>,
+    mut shootout: ResMut<Shootout>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+) {
+    let drawn = stats.teams[0].goals == stats.teams[1].goals && stats.penalties.is_none();
+    if keys.just_pressed(KeyCode::KeyS) && clock.is_full_time() && drawn && !entry.is_active() {
+        *shootout = Shootout::new(true);
+        next_mode.set(GameMode::Shootout);
+    }
+}
+
+/// Clears the pitch down to the ball, a keeper and the goal.
+fn set_up_shootout(
+    mut commands: Commands,
+    mut shootout: ResMut<Shootout>,
+    asset_server: Res<AssetServer>,
+    cleared: Query<Entity, Or<(With<Player>, With<Marker>, With<ObstacleVariant>)>>,
+    mut screens: Query<&mut Visibility, With<FullTimeScreen>>,
+) {
+    *shootout = Shootout::new(shootout.tie_breaker);
+
+    for entity in &cleared {
+        commands
+            .entity(entity)
+            .insert((ColliderDisabled, Visibility::Hidden));
+    }
+    for mut visibility in &mut screens {
+        *visibility = Visibility::Hidden;
+    }
+
+    let transform = Transform::from_translation(KEEPER_START.extend(2.));
+    commands.spawn((
+        SpriteBundle {
+            transform,
+            sprite: Sprite {
+                custom_size: Some(KEEPER_SIZE),
+                ..default()
+            },
+            ..default()
+        },
+        Keeper,
+        Interpolated::new(transform),
+        RigidBody::KinematicPositionBased,
+        Collider::cuboid(KEEPER_SIZE.x / 2., KEEPER_SIZE.y / 2.),
+    ));
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform::from_translation(PENALTY_SPOT.extend(2.)),
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(1., 3.)),
+                anchor: Anchor::CenterLeft,
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        AimArrow,
+    ));
+
+    let style = TextStyle {
+        color: Color::WHITE,
+        font_size: 16.,
+        font: asset_server.load("fonts/More 15 Basic.ttf"),
+    };
+    commands.spawn((
+        TextBundle::from_section("", style)
+            .with_text_justify(JustifyText::Center)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                top: Val::Px(10.),
+                ..default()
+            }),
+        Scoreboard,
+        UI_LAYER,
+    ));
+}
+
+/// Obstacles built after the pitch was cleared, at launch or by a new layout
+/// or seed, are kept out of the way too.
+fn hide_new_obstacles(mut commands: Commands, obstacles: Query<Entity, Added<ObstacleVariant>>) {
+    for entity in &obstacles {
+        commands
+            .entity(entity)
+            .insert((ColliderDisabled, Visibility::Hidden));
+    }
+}
+
+/// Settles the drawn match with the shootout's winner, in its stats, the
+/// active profile's record and the history, before going back to full time.
+fn record_tie_breaker(
+    shootout: Res<Shootout>,
+    mut stats: ResMut<MatchStats>,
+    mut save: ResMut<SaveData>,
+    versus: Res<Versus>,
+) {
+    let Some(winner) = shootout.score.winner() else {
+        return;
+    };
+    let goals = shootout.score.goals().map(|goals| goals as u32);
+    stats.penalties = Some(goals);
+
+    let won = save.active_profile_mut().map(|profile| {
+        let won = profile.team(*versus) == winner;
+        profile.stats.record_shootout(won);
+        won
+    });
+    let Some(mut result) = save.high_scores.history.first().cloned() else {
+        return;
+    };
+    result.penalties = Some((goals[winner], goals[1 - winner]));
+    if won == Some(false) {
+        result.profile = None;
+    }
+    save.high_scores.amend_latest(result);
+}
+
+/// Puts the pitch back the way it was, with everyone lined up for kickoff.
+fn tear_down_shootout(
+    mut commands: Commands,
+    mut last_touch: ResMut<LastTouch>,
+    spawned: Query<Entity, Or<(With<Keeper>, With<AimArrow>, With<Scoreboard>)>>,
+    cleared: Query<Entity, Or<(With<Player>, With<Marker>, With<ObstacleVariant>)>>,
+    mut players: Query<(&Team, &PlayerNumber, &mut Transform), With<Player>>,
+    balls: Query<Entity, With<Ball>>,
+    mut screens: Query<&mut Visibility, With<FullTimeScreen>>,
+) {
+    for entity in &spawned {
+        commands.entity(entity).despawn_recursive();
+    }
+    for entity in &cleared {
+        commands
+            .entity(entity)
+            .remove::<ColliderDisabled>()
+            .insert(Visibility::Inherited);
+    }
+    for (team, number, mut transform) in &mut players {
+        transform.translation = starting_position(team.0, number.0);
+    }
+    // The ball plugin serves a fresh one at kickoff.
+    for ball in &balls {
+        commands.entity(ball).despawn();
+    }
+    last_touch.0 = None;
+    for mut visibility in &mut screens {
+        *visibility = Visibility::Inherited;
+    }
+}
+
+fn place_ball(
+    mut shootout: ResMut<Shootout>,
+    mut balls: Query<(&mut Transform, &mut Velocity), With<Ball>>,
+    mut keepers: Query<(&mut Transform, &mut Sprite), (With<Keeper>, Without<Ball>)>,
+) {
+    if shootout.phase != KickPhase::Setup {
+        return;
+    }
+    // Goals take the ball away, and there's a new one on the next tick.
+    let Ok((mut transform, mut velocity)) = balls.get_single_mut() else {
+        return;
+    };
+
+    transform.translation = PENALTY_SPOT.extend(1.);
+    *velocity = Velocity::zero();
+
+    let keeping_team = 1 - shootout.score.kicking_team();
+    for (mut transform, mut sprite) in &mut keepers {
+        transform.translation = KEEPER_START.extend(2.);
+        sprite.color = TEAM_COLORS[keeping_team];
+    }
+
+    shootout.aim = 0.;
+    shootout.power = 0.;
+    shootout.dive = Dive::Stay;
+    shootout.phase = KickPhase::Aiming;
+}
+
+/// The keeper's up and down keys pick a side, up to the moment of the kick.
+fn choose_dive(
+    keys: Res<ButtonInput<KeyCode>>,
+    save: Res<SaveData>,
+    versus: Res<Versus>,
+    mut shootout: ResMut<Shootout>,
+) {
+    if shootout.phase != KickPhase::Aiming {
+        return;
+    }
+
+    let bindings = save.bindings(1 - shootout.score.kicking_team(), *versus);
+    if keys.pressed(bindings.up) {
+        shootout.dive = Dive::Up;
+    } else if keys.pressed(bindings.down) {
+        shootout.dive = Dive::Down;
+    }
+}
+
+/// The kicker's up and down keys aim, and holding their run key builds up
+/// power. Letting go shoots.
+fn take_kick(
+    keys: Res<ButtonInput<KeyCode>>,
+    save: Res<SaveData>,
+    versus: Res<Versus>,
+    time: Res<Time>,
+    mut shootout: ResMut<Shootout>,
+    mut balls: Query<&mut Velocity, With<Ball>>,
+) {
+    if shootout.phase != KickPhase::Aiming {
+        return;
+    }
+
+    let bindings = save.bindings(shootout.score.kicking_team(), *versus);
+    let delta = time.delta_seconds();
+    if keys.pressed(bindings.up) {
+        shootout.aim += AIM_SPEED * delta;
+    }
+    if keys.pressed(bindings.down) {
+        shootout.aim -= AIM_SPEED * delta;
+    }
+    shootout.aim = shootout.aim.clamp(-MAX_AIM, MAX_AIM);
+
+    let charging = keys.pressed(bindings.run);
+    if charging {
+        shootout.power = (shootout.power + POWER_RATE * delta).min(1.);
+    }
+    let released = !charging && shootout.power > 0.;
+    if !released && shootout.power < 1. {
+        return;
+    }
+
+    let speed = MIN_SHOT_SPEED + (MAX_SHOT_SPEED - MIN_SHOT_SPEED) * shootout.power;
+    for mut velocity in &mut balls {
+        velocity.linvel = Vec2::from_angle(shootout.aim) * speed;
+    }
+    shootout.phase = KickPhase::InFlight { ticks: 0 };
+}
+
+fn dive(
+    time: Res<Time>,
+    shootout: Res<Shootout>,
+    mut keepers: Query<&mut Transform, With<Keeper>>,
+) {
+    if !matches!(shootout.phase, KickPhase::InFlight { .. }) {
+        return;
+    }
+
+    let target = KEEPER_START.y
+        + match shootout.dive {
+            Dive::Stay => 0.,
+            Dive::Up => DIVE_REACH,
+            Dive::Down => -DIVE_REACH,
+        };
+    let step = DIVE_SPEED * time.delta_seconds();
+    for mut transform in &mut keepers {
+        let y = transform.translation.y;
+        transform.translation.y = y + (target - y).clamp(-step, step);
+    }
+}
+
+fn judge_kick(
+    mut shootout: ResMut<Shootout>,
+    mut goal_events: EventReader<GoalEvent>,
+    goals: Query<&Transform>,
+) {
+    // Only the goal being shot at counts.
+    let scored = goal_events.read().any(|goal_event| {
+        goals
+            .get(goal_event.goal)
+            .is_ok_and(|goal| goal.translation.x > 0.)
+    });
+
+    shootout.phase = match shootout.phase {
+        KickPhase::InFlight { ticks } if scored || ticks + 1 >= FLIGHT_TICKS => {
+            shootout.score.record(scored);
+            KickPhase::Result {
+                goal: scored,
+                ticks: 0,
+            }
+        }
+        KickPhase::InFlight { ticks } => KickPhase::InFlight { ticks: ticks + 1 },
+        KickPhase::Result { ticks, .. } if ticks + 1 >= RESULT_TICKS => {
+            if shootout.score.winner().is_some() {
+                KickPhase::Finished
+            } else {
+                KickPhase::Setup
+            }
+        }
+        KickPhase::Result { goal, ticks } => KickPhase::Result {
+            goal,
+            ticks: ticks + 1,
+        },
+        phase => phase,
+    };
+}
+
+fn update_scoreboard(
+    shootout: Res<Shootout>,
+    save: Res<SaveData>,
+    versus: Res<Versus>,
+    mut scoreboards: Query<&mut Text, With<Scoreboard>>,
+) {
+    let score = &shootout.score;
+    let slots = score.kicks.iter().map(Vec::len).fold(ROUNDS, usize::max);
+    let goals = score.goals();
+
+    let mut lines = vec!["PENALTIES".to_string()];
+    for team in 0..NUM_TEAMS {
+        let markers: Vec<&str> = (0..slots)
+            .map(|kick| match score.kicks[team].get(kick) {
+                Some(true) => "O",
+                Some(false) => "X",
+                None => "-",
+            })
+            .collect();
+        lines.push(format!(
+            "{:<5} {}  {}",
+            TEAM_NAMES[team],
+            markers.join(" "),
+            goals[team]
+        ));
+    }
+    lines.push(String::new());
+
+    let kicker = score.kicking_team();
+    let keeper = 1 - kicker;
+    match shootout.phase {
+        KickPhase::Setup | KickPhase::Aiming | KickPhase::InFlight { .. } => {
+            let kicker_keys = save.bindings(kicker, *versus);
+            let keeper_keys = save.bindings(keeper, *versus);
+            if score.kicks[kicker].len() >= ROUNDS {
+                lines.push("SUDDEN DEATH".to_string());
+            }
+            lines.push(format!(
+                "{} to kick: {:?}/{:?} aim, hold {:?} to shoot",
+                TEAM_NAMES[kicker], kicker_keys.up, kicker_keys.down, kicker_keys.run
+            ));
+            lines.push(format!(
+                "{} in goal: {:?}/{:?} to dive",
+                TEAM_NAMES[keeper], keeper_keys.up, keeper_keys.down
+            ));
+        }
+        KickPhase::Result { goal, .. } => {
+            lines.push(if goal { "GOAL!" } else { "NO GOAL" }.to_string());
+        }
+        KickPhase::Finished => {
+            let winner = score.winner().unwrap_or_default();
+            lines.push(format!(
+                "{} win {}-{} on penalties",
+                TEAM_NAMES[winner],
+                goals[winner],
+                goals[1 - winner]
+            ));
+            let next = if shootout.tie_breaker {
+                "ENTER back to full time"
+            } else {
+                "ENTER new match"
+            };
+            lines.push(next.to_string());
+        }
+    }
+
+    for mut text in &mut scoreboards {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+/// Points where the kick is going, growing with its power.
+fn update_aim_arrow(
+    shootout: Res<Shootout>,
+    mut arrows: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<AimArrow>>,
+) {
+    for (mut transform, mut sprite, mut visibility) in &mut arrows {
+        *visibility = if shootout.phase == KickPhase::Aiming {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        transform.rotation = Quat::from_rotation_z(shootout.aim);
+        transform.scale.x = ARROW_LENGTH * (0.25 + shootout.power);
+        sprite.color = TEAM_COLORS[shootout.score.kicking_team()];
+    }
+}
+
+fn leave_shootout(
+    keys: Res<ButtonInput<KeyCode>>,
+    shootout: Res<Shootout>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+) {
+    if shootout.is_finished() && keys.just_pressed(KeyCode::Enter) {
+        next_mode.set(GameMode::Match);
+    }
+}
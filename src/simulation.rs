@@ -0,0 +1,103 @@
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_rapier2d::prelude::*;
+
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Time::<Fixed>::from_hz(SIMULATION_HZ))
+            .configure_sets(FixedUpdate, GameplaySet.before(PhysicsSet::SyncBackend))
+            .add_systems(Startup, use_fixed_physics_step)
+            .add_systems(FixedFirst, restore_simulated_transforms)
+            .add_systems(FixedLast, store_simulated_transforms)
+            .add_systems(
+                PostUpdate,
+                interpolate_transforms.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Gameplay ticks per second. Everything that affects the outcome of a match
+/// runs at this rate, whatever the frame rate, so the same inputs always
+/// play out the same way.
+pub const SIMULATION_HZ: f64 = 64.;
+
+/// Gameplay systems in `FixedUpdate`. They run before Rapier picks up the
+/// changes they make and steps the world.
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GameplaySet;
+
+/// Draws an entity between its last two simulated positions, so motion stays
+/// smooth when frames and ticks don't line up.
+///
+/// `Transform` holds the rendered position between ticks and is put back to
+/// the simulated one before the next tick. Anything else that moves the
+/// entity, like the editor or a replay, is picked up as a jump.
+#[derive(Component)]
+pub struct Interpolated {
+    previous: Transform,
+    current: Transform,
+    /// What was last drawn, if it hasn't been simulated since.
+    rendered: Option<Transform>,
+}
+
+impl Interpolated {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            previous: transform,
+            current: transform,
+            rendered: None,
+        }
+    }
+
+    fn jump_to(&mut self, transform: Transform) {
+        self.previous = transform;
+        self.current = transform;
+    }
+}
+
+/// Rapier steps once per tick by exactly the tick length.
+fn use_fixed_physics_step(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.timestep_mode = TimestepMode::Fixed {
+        dt: (1. / SIMULATION_HZ) as f32,
+        substeps: 1,
+    };
+}
+
+fn restore_simulated_transforms(mut query: Query<(&mut Transform, &mut Interpolated)>) {
+    for (mut transform, mut interpolated) in &mut query {
+        match interpolated.rendered.take() {
+            Some(rendered) if rendered == *transform => *transform = interpolated.current,
+            _ => interpolated.jump_to(*transform),
+        }
+    }
+}
+
+fn store_simulated_transforms(mut query: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in &mut query {
+        interpolated.previous = interpolated.current;
+        interpolated.current = *transform;
+    }
+}
+
+pub(crate) fn interpolate_transforms(
+    time: Res<Time<Fixed>>,
+    mut query: Query<(&mut Transform, &mut Interpolated)>,
+) {
+    let blend = time.overstep_fraction();
+
+    for (mut transform, mut interpolated) in &mut query {
+        let expected = interpolated.rendered.unwrap_or(interpolated.current);
+        if *transform != expected {
+            interpolated.jump_to(*transform);
+        }
+
+        let Interpolated {
+            previous, current, ..
+        } = *interpolated;
+        transform.translation = previous.translation.lerp(current.translation, blend);
+        transform.rotation = previous.rotation.slerp(current.rotation, blend);
+        transform.scale = previous.scale.lerp(current.scale, blend);
+        interpolated.rendered = Some(*transform);
+    }
+}
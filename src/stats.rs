@@ -0,0 +1,476 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::{attacking_team, Goal, GoalEvent, GOAL_HEIGHT},
+    ball::{track_last_touch, Ball, LastTouch},
+    camera::UI_LAYER,
+    clock::{FullTime, MatchClock, NewMatch},
+    mode::{resume_simulation, GameMode},
+    obstacle::{ball_collision, ObstacleVariant},
+    player::{
+        starting_position, Player, PlayerNumber, Team, TeamSize, Versus, NUM_TEAMS, TEAM_NAMES,
+    },
+    save::SaveData,
+    simulation::GameplaySet,
+};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchStats>()
+            .add_event::<Shot>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    count_touches.before(track_last_touch),
+                    count_deflections,
+                    count_goals,
+                    count_possession.after(track_last_touch),
+                    count_distance_run,
+                )
+                    .in_set(GameplaySet)
+                    .run_if(in_state(GameMode::Match)),
+            )
+            .add_systems(
+                Update,
+                (
+                    spawn_full_time_screen.run_if(on_event::<FullTime>()),
+                    update_full_time_footer.run_if(resource_changed::<MatchStats>),
+                    (start_new_match, resume_simulation)
+                        .chain()
+                        .run_if(in_state(GameMode::Match).and_then(new_match_requested)),
+                ),
+            );
+    }
+}
+
+/// Slower touches are dribbles, not shots.
+const SHOT_SPEED: f32 = 250.;
+
+/// What each team has done so far this match.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct TeamStats {
+    /// Seconds of play with one of this team's players last on the ball.
+    pub possession: f32,
+    /// Touches that sent the ball toward the other team's goal at
+    /// `SHOT_SPEED` or more.
+    pub shots: u32,
+    /// Shots that were heading between the posts.
+    pub shots_on_target: u32,
+    /// Touches that followed a touch by a teammate.
+    pub passes: u32,
+    /// Touches that took the ball from the other team.
+    pub tackles: u32,
+    pub goals: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PlayerStats {
+    pub team: usize,
+    pub number: usize,
+    /// In pixels.
+    pub distance_run: f32,
+    pub touches: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct GoalRecord {
+    /// Seconds into the match.
+    pub time: f32,
+    /// The team the goal counts for.
+    pub team: usize,
+    /// Who last touched the ball, as `(team, number)`. Might be on the other
+    /// team, for an own goal.
+    pub scorer: Option<(usize, usize)>,
+}
+
+/// Running totals for the match, reset when a new one starts.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct MatchStats {
+    pub teams: [TeamStats; NUM_TEAMS],
+    pub players: Vec<PlayerStats>,
+    /// Times the ball bounced off an obstacle.
+    pub deflections: u32,
+    pub goals: Vec<GoalRecord>,
+    /// Penalties each team scored, when the draw was settled by a shootout.
+    #[serde(default)]
+    pub penalties: Option<[u32; NUM_TEAMS]>,
+}
+
+impl MatchStats {
+    pub fn player(&self, team: usize, number: usize) -> Option<&PlayerStats> {
+        self.players
+            .iter()
+            .find(|player| player.team == team && player.number == number)
+    }
+
+    fn player_mut(&mut self, team: usize, number: usize) -> &mut PlayerStats {
+        let index = match self
+            .players
+            .iter()
+            .position(|player| player.team == team && player.number == number)
+        {
+            Some(index) => index,
+            None => {
+                self.players.push(PlayerStats {
+                    team,
+                    number,
+                    distance_run: 0.,
+                    touches: 0,
+                });
+                self.players.len() - 1
+            }
+        };
+        &mut self.players[index]
+    }
+
+    /// In pixels, added up over the team's players.
+    pub fn distance_run(&self, team: usize) -> f32 {
+        self.players
+            .iter()
+            .filter(|player| player.team == team)
+            .map(|player| player.distance_run)
+            .sum()
+    }
+
+    /// Each team's share of possession, from 0 to 1.
+    pub fn possession_share(&self) -> [f32; NUM_TEAMS] {
+        let total: f32 = self.teams.iter().map(|team| team.possession).sum();
+        self.teams.each_ref().map(|team| {
+            if total > 0. {
+                team.possession / total
+            } else {
+                1. / NUM_TEAMS as f32
+            }
+        })
+    }
+}
+
+/// Sent when a touch counts as a shot.
+#[derive(Event, Clone, Debug)]
+pub struct Shot {
+    pub player: Entity,
+    pub on_target: bool,
+}
+
+#[derive(Component)]
+pub(crate) struct FullTimeScreen;
+
+/// The goals and hotkeys under the table, which change once a draw has been
+/// settled on penalties.
+#[derive(Component)]
+struct FullTimeFooter;
+
+/// Runs before `LastTouch` is updated, so it can tell who had the ball
+/// before each touch.
+fn count_touches(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut stats: ResMut<MatchStats>,
+    mut shots: EventWriter<Shot>,
+    last_touch: Res<LastTouch>,
+    balls: Query<(&Transform, &Velocity), With<Ball>>,
+    players: Query<(&Team, &PlayerNumber), With<Player>>,
+    goals: Query<&Transform, With<Goal>>,
+) {
+    let mut previous = last_touch.0.and_then(|entity| players.get(entity).ok());
+
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(entity1, entity2, _flags) = collision_event else {
+            continue;
+        };
+        for (ball, player) in [(*entity1, *entity2), (*entity2, *entity1)] {
+            let (Ok((ball, velocity)), Ok(toucher)) = (balls.get(ball), players.get(player)) else {
+                continue;
+            };
+            let (team, number) = (toucher.0 .0, toucher.1 .0);
+
+            stats.player_mut(team, number).touches += 1;
+            match previous {
+                Some((previous_team, previous_number))
+                    if previous_team.0 == team && previous_number.0 != number =>
+                {
+                    stats.teams[team].passes += 1;
+                }
+                Some((previous_team, _)) if previous_team.0 != team => {
+                    stats.teams[team].tackles += 1;
+                }
+                _ => {}
+            }
+            previous = Some(toucher);
+
+            let position = ball.translation.truncate();
+            let targets: Vec<Vec3> = goals
+                .iter()
+                .map(|goal| goal.translation)
+                .filter(|goal| attacking_team(*goal) == team)
+                .collect();
+            let heading_for_goal = targets
+                .iter()
+                .any(|goal| (goal.x - position.x).signum() == velocity.linvel.x.signum());
+            if !heading_for_goal || velocity.linvel.length() < SHOT_SPEED {
+                continue;
+            }
+
+            let on_target = targets
+                .iter()
+                .any(|goal| on_target(position, velocity.linvel, *goal));
+            stats.teams[team].shots += 1;
+            if on_target {
+                stats.teams[team].shots_on_target += 1;
+            }
+            shots.send(Shot { player, on_target });
+        }
+    }
+}
+
+/// Whether a ball at `position` moving at `velocity` would cross the goal
+/// line between the posts, if nothing got in the way.
+fn on_target(position: Vec2, velocity: Vec2, goal: Vec3) -> bool {
+    if velocity.x == 0. {
+        return false;
+    }
+    let time = (goal.x - position.x) / velocity.x;
+    let crossing = position.y + velocity.y * time;
+    time > 0. && (crossing - goal.y).abs() < GOAL_HEIGHT / 2.
+}
+
+fn count_deflections(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut stats: ResMut<MatchStats>,
+    balls: Query<Entity, With<Ball>>,
+    obstacles: Query<(), (With<ObstacleVariant>, Without<Sensor>)>,
+) {
+    for collision_event in collision_events.read() {
+        if let Some((_, other)) = ball_collision(collision_event, &balls) {
+            if obstacles.contains(other) {
+                stats.deflections += 1;
+            }
+        }
+    }
+}
+
+fn count_goals(
+    mut goal_events: EventReader<GoalEvent>,
+    mut stats: ResMut<MatchStats>,
+    clock: Res<MatchClock>,
+    goals: Query<&Transform, With<Goal>>,
+    players: Query<(&Team, &PlayerNumber), With<Player>>,
+) {
+    for goal_event in goal_events.read() {
+        let Ok(goal) = goals.get(goal_event.goal) else {
+            continue;
+        };
+        let team = attacking_team(goal.translation);
+        let scorer = goal_event
+            .scorer
+            .and_then(|scorer| players.get(scorer).ok())
+            .map(|(team, number)| (team.0, number.0));
+
+        stats.teams[team].goals += 1;
+        stats.goals.push(GoalRecord {
+            time: clock.elapsed.as_secs_f32(),
+            team,
+            scorer,
+        });
+    }
+}
+
+fn count_possession(
+    time: Res<Time>,
+    mut stats: ResMut<MatchStats>,
+    last_touch: Res<LastTouch>,
+    players: Query<&Team, With<Player>>,
+) {
+    if let Some(team) = last_touch.0.and_then(|entity| players.get(entity).ok()) {
+        stats.teams[team.0].possession += time.delta_seconds();
+    }
+}
+
+/// Rapier only updates a controller's output on ticks it moved.
+fn count_distance_run(
+    mut stats: ResMut<MatchStats>,
+    players: Query<
+        (&Team, &PlayerNumber, &KinematicCharacterControllerOutput),
+        Changed<KinematicCharacterControllerOutput>,
+    >,
+) {
+    for (team, number, output) in &players {
+        stats.player_mut(team.0, number.0).distance_run += output.effective_translation.length();
+    }
+}
+
+fn spawn_full_time_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    stats: Res<MatchStats>,
+    team_size: Res<TeamSize>,
+    versus: Res<Versus>,
+) {
+    let font = asset_server.load("fonts/More 15 Basic.ttf");
+    let style = |font_size: f32| TextStyle {
+        color: Color::WHITE,
+        font_size,
+        font: font.clone(),
+    };
+
+    let share = stats.possession_share();
+    let team_row = |label: &str, value: &dyn Fn(usize) -> String| {
+        let mut row = vec![label.to_string()];
+        row.extend((0..NUM_TEAMS).map(value));
+        row
+    };
+    // At 100 pixels to the metre, as in the physics.
+    let metres = |pixels: f32| format!("{:.0}m", pixels / 100.);
+    let mut rows = vec![
+        team_row("", &|team| TEAM_NAMES[team].to_string()),
+        team_row("Goals", &|team| stats.teams[team].goals.to_string()),
+        team_row("Possession", &|team| format!("{:.0}%", share[team] * 100.)),
+        team_row("Shots", &|team| stats.teams[team].shots.to_string()),
+        team_row("On target", &|team| {
+            stats.teams[team].shots_on_target.to_string()
+        }),
+        team_row("Passes", &|team| stats.teams[team].passes.to_string()),
+        team_row("Tackles", &|team| stats.teams[team].tackles.to_string()),
+        team_row("Distance run", &|team| metres(stats.distance_run(team))),
+    ];
+    // Then each player's share of it, by their number in the line-up.
+    for number in 0..team_size.0 {
+        rows.push(team_row(&format!("  #{}", number + 1), &|team| {
+            if team >= versus.teams() {
+                return "-".to_string();
+            }
+            metres(
+                stats
+                    .player(team, number)
+                    .map_or(0., |player| player.distance_run),
+            )
+        }));
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(2.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.8).into(),
+                ..default()
+            },
+            FullTimeScreen,
+            UI_LAYER,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("FULL TIME", style(28.)));
+            for row in rows {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for (column, cell) in row.into_iter().enumerate() {
+                            let width = if column == 0 { 140. } else { 70. };
+                            parent.spawn(TextBundle::from_section(cell, style(16.)).with_style(
+                                Style {
+                                    width: Val::Px(width),
+                                    ..default()
+                                },
+                            ));
+                        }
+                    });
+            }
+            parent.spawn((
+                TextBundle::from_section(full_time_footer(&stats), style(16.)).with_style(Style {
+                    margin: UiRect::top(Val::Px(8.)),
+                    ..default()
+                }),
+                FullTimeFooter,
+            ));
+        });
+}
+
+fn full_time_footer(stats: &MatchStats) -> String {
+    let mut footer = format!("Obstacle deflections: {}\n", stats.deflections);
+    for goal in &stats.goals {
+        let seconds = goal.time as u32;
+        let scorer = match goal.scorer {
+            Some((team, number)) if team == goal.team => {
+                format!("{} #{}", TEAM_NAMES[team], number + 1)
+            }
+            Some((team, number)) => format!("{} #{} (own goal)", TEAM_NAMES[team], number + 1),
+            None => TEAM_NAMES[goal.team].to_string(),
+        };
+        footer += &format!("{}:{:02} {}\n", seconds / 60, seconds % 60, scorer);
+    }
+    if let Some(penalties) = stats.penalties {
+        let winner = if penalties[0] > penalties[1] { 0 } else { 1 };
+        footer += &format!(
+            "{} win {}-{} on penalties\n",
+            TEAM_NAMES[winner],
+            penalties[winner],
+            penalties[1 - winner]
+        );
+    }
+    footer += "\nH heatmaps   L high scores   ENTER new match";
+    if stats.teams[0].goals == stats.teams[1].goals && stats.penalties.is_none() {
+        footer += "   S penalties";
+    }
+    footer
+}
+
+fn update_full_time_footer(
+    stats: Res<MatchStats>,
+    mut footers: Query<&mut Text, With<FullTimeFooter>>,
+) {
+    for mut text in &mut footers {
+        text.sections[0].value = full_time_footer(&stats);
+    }
+}
+
+fn new_match_requested(keys: Res<ButtonInput<KeyCode>>, clock: Res<MatchClock>) -> bool {
+    clock.is_full_time() && keys.just_pressed(KeyCode::Enter)
+}
+
+/// Lines everyone back up for kickoff with the score, clock and stats reset.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn start_new_match(
+    mut commands: Commands,
+    mut clock: ResMut<MatchClock>,
+    mut new_match: EventWriter<NewMatch>,
+    mut stats: ResMut<MatchStats>,
+    mut save: ResMut<SaveData>,
+    mut last_touch: ResMut<LastTouch>,
+    balls: Query<Entity, With<Ball>>,
+    mut players: Query<(&Team, &PlayerNumber, &mut Transform), With<Player>>,
+    screens: Query<Entity, With<FullTimeScreen>>,
+) {
+    clock.elapsed = default();
+    new_match.send(NewMatch);
+    *stats = default();
+    save.score = 0;
+    last_touch.0 = None;
+
+    // The ball plugin serves a fresh one at kickoff.
+    for ball in &balls {
+        commands.entity(ball).despawn();
+    }
+    for (team, number, mut transform) in &mut players {
+        transform.translation = starting_position(team.0, number.0);
+    }
+    for screen in &screens {
+        commands.entity(screen).despawn_recursive();
+    }
+}
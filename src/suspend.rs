@@ -0,0 +1,218 @@
+use std::time::Duration;
+
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::clear_score,
+    ball::Ball,
+    camera::UI_LAYER,
+    clock::MatchClock,
+    mode::{pause_simulation, resume_simulation, GameMode},
+    obstacle::ObstacleLayout,
+    player::TEAM_NAMES,
+    recording::{MatchState, MatchViewer, Snapshot},
+    replay::restore_live_poses,
+    save::{write_save_data, SaveData},
+    stats::MatchStats,
+};
+
+pub struct SuspendPlugin;
+
+impl Plugin for SuspendPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ContinueChoice>()
+            .add_systems(
+                Update,
+                (
+                    (offer_continue, pause_simulation)
+                        .chain()
+                        .run_if(continue_offer_due),
+                    choose_continue,
+                    (resolve_continue, resume_simulation)
+                        .chain()
+                        .run_if(on_event::<ContinueChoice>()),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Last,
+                (
+                    restore_live_poses.run_if(in_state(GameMode::Replay)),
+                    suspend_match,
+                )
+                    .chain()
+                    .before(clear_score)
+                    .before(write_save_data)
+                    .run_if(on_event::<AppExit>()),
+            );
+    }
+}
+
+/// A match left unfinished when the game was closed, kept in the save so it
+/// can be picked back up on the next launch.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SuspendedMatch {
+    /// Score, ball, players, possession and obstacles, including how far
+    /// through their movement each one was. Players have no velocity of their
+    /// own: they only move while their keys are held.
+    snapshot: Snapshot,
+    elapsed: Duration,
+    stats: MatchStats,
+    layout: ObstacleLayout,
+}
+
+impl SuspendedMatch {
+    /// How long it had been going.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn stats(&self) -> &MatchStats {
+        &self.stats
+    }
+}
+
+/// Whether to pick the suspended match back up. `Enter` or `Space` on the
+/// prompt at launch sends it.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContinueChoice {
+    Continue,
+    NewMatch,
+}
+
+#[derive(Component)]
+struct ContinueScreen;
+
+/// Once, as soon as a match has spawned the ball, so there's something to
+/// put back where it was. Launched into another mode, it waits until there's
+/// a match to put it in.
+fn continue_offer_due(
+    mut offered: Local<bool>,
+    mode: Res<State<GameMode>>,
+    save: Res<SaveData>,
+    balls: Query<(), With<Ball>>,
+) -> bool {
+    if *offered || *mode.get() != GameMode::Match || balls.is_empty() {
+        return false;
+    }
+    *offered = true;
+    save.suspended.is_some()
+}
+
+fn offer_continue(mut commands: Commands, save: Res<SaveData>, asset_server: Res<AssetServer>) {
+    let Some(suspended) = &save.suspended else {
+        return;
+    };
+
+    let goals = &suspended.stats.teams;
+    let seconds = suspended.elapsed.as_secs();
+    let text = format!(
+        "CONTINUE MATCH?\n{} {} - {} {}   {}:{:02} played\n\nENTER continue   SPACE new match",
+        TEAM_NAMES[0],
+        goals[0].goals,
+        goals[1].goals,
+        TEAM_NAMES[1],
+        seconds / 60,
+        seconds % 60
+    );
+    let style = TextStyle {
+        color: Color::WHITE,
+        font_size: 16.,
+        font: asset_server.load("fonts/More 15 Basic.ttf"),
+    };
+    commands.spawn((
+        TextBundle::from_section(text, style)
+            .with_text_justify(JustifyText::Center)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                top: Val::Percent(35.),
+                ..default()
+            })
+            .with_background_color(Color::rgba(0., 0., 0., 0.9)),
+        ContinueScreen,
+        UI_LAYER,
+    ));
+}
+
+fn choose_continue(
+    keys: Res<ButtonInput<KeyCode>>,
+    screens: Query<(), With<ContinueScreen>>,
+    mut choices: EventWriter<ContinueChoice>,
+) {
+    if screens.is_empty() {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Enter) {
+        choices.send(ContinueChoice::Continue);
+    } else if keys.just_pressed(KeyCode::Space) {
+        choices.send(ContinueChoice::NewMatch);
+    }
+}
+
+/// Either way the suspended match is used up, so it's dropped from the save.
+fn resolve_continue(
+    mut commands: Commands,
+    mut choices: EventReader<ContinueChoice>,
+    mut state: MatchState,
+    mut clock: ResMut<MatchClock>,
+    mut stats: ResMut<MatchStats>,
+    mut layout: ResMut<ObstacleLayout>,
+    screens: Query<Entity, With<ContinueScreen>>,
+) {
+    for screen in &screens {
+        commands.entity(screen).despawn_recursive();
+    }
+
+    let Some(choice) = choices.read().last().copied() else {
+        return;
+    };
+    let Some(suspended) = state.save.suspended.take() else {
+        return;
+    };
+    if choice == ContinueChoice::NewMatch {
+        return;
+    }
+
+    state.restore(&suspended.snapshot, true);
+    clock.elapsed = suspended.elapsed;
+    *stats = suspended.stats;
+    // The obstacles have just been rebuilt from the snapshot, and changing
+    // the layout would build them again from scratch.
+    *layout.bypass_change_detection() = suspended.layout;
+}
+
+/// Keeps the match in the save when the game is closed partway through,
+/// including during a replay, in the editor or while watching a recording.
+/// It has to run before `clear_score` resets the score for the next launch.
+fn suspend_match(
+    mode: Res<State<GameMode>>,
+    clock: Res<MatchClock>,
+    stats: Res<MatchStats>,
+    layout: Res<ObstacleLayout>,
+    viewer: Option<Res<MatchViewer>>,
+    mut state: MatchState,
+) {
+    // Closed without answering, or before it was offered, so the old one is
+    // still waiting.
+    if state.save.suspended.is_some() {
+        return;
+    }
+
+    let live = match mode.get() {
+        _ if clock.is_full_time() => None,
+        GameMode::Match | GameMode::Replay | GameMode::Editor => {
+            Some((state.capture(clock.tick()), layout.clone()))
+        }
+        // The live match was put aside to show the recording.
+        GameMode::Viewer => viewer.and_then(|viewer| viewer.resume.clone()),
+        GameMode::Shootout => None,
+    };
+    state.save.suspended = live.map(|(snapshot, layout)| SuspendedMatch {
+        snapshot,
+        elapsed: clock.elapsed,
+        stats: stats.clone(),
+        layout,
+    });
+}
@@ -1,7 +1,13 @@
 use bevy::prelude::*;
-use bevy_pkv::PkvStore;
 
-use crate::{camera::UI_LAYER, constants::*};
+use crate::{
+    camera::UI_LAYER,
+    clock::MatchClock,
+    constants::*,
+    obstacle::{arena_name, ObstacleLayout},
+    procedural::SeedEntry,
+    save::SaveData,
+};
 
 pub struct UIPlugin;
 
@@ -12,11 +18,10 @@ impl Plugin for UIPlugin {
     }
 }
 
-fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>, pkv_store: Res<PkvStore>) {
+fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>, save: Res<SaveData>) {
     let font = asset_server.load("fonts/More 15 Basic.ttf");
 
-    let high_score = pkv_store.get::<i32>("high_score").unwrap_or(0);
-    let score = pkv_store.get::<i32>("score").unwrap_or(0);
+    let score = save.score;
 
     let text_style = TextStyle {
         color: Color::WHITE,
@@ -28,17 +33,13 @@ fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>, pkv_store: R
         .spawn((
             NodeBundle {
                 style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(0.),
                     width: Val::Percent(100.),
                     height: Val::Px(UI_HEIGHT),
                     flex_direction: FlexDirection::Row,
                     justify_content: JustifyContent::SpaceEvenly,
                     align_items: AlignItems::Center,
-                    margin: UiRect {
-                        left: Val::Px(0.),
-                        right: Val::Px(0.),
-                        top: Val::Px(GROUND_HEIGHT),
-                        bottom: Val::Px(0.),
-                    },
                     ..default()
                 },
                 ..default()
@@ -47,13 +48,15 @@ fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>, pkv_store: R
         ))
         .with_children(|parent| {
             parent.spawn((
-                TextBundle::from_section(format!("Hi Score {}", high_score), text_style.clone()),
+                TextBundle::from_section("", text_style.clone()),
                 HighScoreText,
             ));
             parent.spawn((
                 TextBundle::from_section(format!("Score {}", score), text_style.clone()),
                 ScoreText,
             ));
+            parent.spawn((TextBundle::from_section("", text_style.clone()), SeedText));
+            parent.spawn((TextBundle::from_section("", text_style.clone()), ClockText));
         });
 }
 
@@ -63,21 +66,58 @@ struct ScoreText;
 #[derive(Component)]
 struct HighScoreText;
 
+#[derive(Component)]
+struct SeedText;
+
+#[derive(Component)]
+struct ClockText;
+
 fn update_ui(
     mut params: ParamSet<(
         Query<&mut Text, With<ScoreText>>,
         Query<&mut Text, With<HighScoreText>>,
+        Query<&mut Text, With<SeedText>>,
+        Query<&mut Text, With<ClockText>>,
     )>,
-    pkv: Res<PkvStore>,
+    save: Res<SaveData>,
+    layout: Res<ObstacleLayout>,
+    seed_entry: Res<SeedEntry>,
+    clock: Res<MatchClock>,
 ) {
-    let score = pkv.get::<i32>("score").unwrap_or(0);
-    let high_score = pkv.get::<i32>("high_score").unwrap_or(0);
+    let score = save.score;
+    let high_score = match save.high_scores.best() {
+        Some(best) => format!("Hi Score: {} {}", best.score(), best.initials),
+        None => "Hi Score: -".to_string(),
+    };
 
     for mut text in &mut params.p0() {
         text.sections[0].value = format!("Score: {}", score);
     }
     for mut text in &mut params.p1() {
-        text.sections[0].value = format!("Hi Score: {}", high_score);
+        text.sections[0].value = high_score.clone();
+    }
+
+    let seed = match (&seed_entry.0, &*layout) {
+        (Some(digits), _) => format!("Seed: {}_", digits),
+        (None, ObstacleLayout::Procedural { seed }) => format!("Seed: {}", seed),
+        (None, ObstacleLayout::Classic) => "Classic".to_string(),
+        (None, ObstacleLayout::File(path)) => arena_name(path),
+    };
+    for mut text in &mut params.p2() {
+        text.sections[0].value = seed.clone();
+    }
+
+    // Rounded up, so the last second reads 0:01 rather than 0:00.
+    let clock = match clock.remaining() {
+        Some(remaining) if remaining.is_zero() => "Full time".to_string(),
+        Some(remaining) => {
+            let seconds = remaining.as_secs_f32().ceil() as u32;
+            format!("{}:{:02}", seconds / 60, seconds % 60)
+        }
+        None => String::new(),
+    };
+    for mut text in &mut params.p3() {
+        text.sections[0].value = clock.clone();
     }
 }
 
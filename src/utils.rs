@@ -27,6 +27,6 @@ pub fn display_events(
     }
 
     for contact_force_event in contact_force_events.read() {
-        println!("Received contact force event: {:?}", contact_force_event);
+        info!("Received contact force event: {:?}", contact_force_event);
     }
 }
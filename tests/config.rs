@@ -0,0 +1,177 @@
+use std::{fs, process, time::Duration};
+
+use bevy::prelude::*;
+use bevy_soccer::{
+    arena::Goal,
+    clock::{FullTime, MatchClock},
+    config::{Arena, ConfigError, LaunchConfig},
+    headless::HeadlessApp,
+    mode::GameMode,
+    obstacle::{ObstacleLayout, ObstacleVariant},
+    player::{Player, TeamSize},
+};
+
+/// Parses `args` as if there were no config file, whatever is in the working
+/// directory.
+fn parse(args: &[&str]) -> Result<LaunchConfig, ConfigError> {
+    LaunchConfig::from_args_with_default(
+        args.iter().map(|arg| arg.to_string()),
+        "tests/no-such-config.ron",
+    )
+}
+
+#[test]
+fn no_flags_gives_the_defaults() {
+    assert_eq!(parse(&[]).unwrap(), LaunchConfig::default());
+}
+
+#[test]
+fn flags_set_the_options() {
+    let config = parse(&[
+        "--window",
+        "1600x800",
+        "--fullscreen",
+        "--arena",
+        "procedural",
+        "--players",
+        "2",
+        "--match-length",
+        "90",
+        "--seed",
+        "42",
+        "--mode",
+        "editor",
+        "--versus",
+    ])
+    .unwrap();
+
+    assert_eq!(config.window, (1600, 800));
+    assert!(config.fullscreen);
+    assert_eq!(config.arena, Arena::Procedural);
+    assert_eq!(config.players_per_team, 2);
+    assert_eq!(config.match_length, Some(90));
+    assert_eq!(config.seed, Some(42));
+    assert_eq!(config.mode, GameMode::Editor);
+    assert!(config.versus);
+}
+
+#[test]
+fn bad_values_are_rejected() {
+    for args in [
+        &["--window", "big"][..],
+        &["--players", "three"],
+        &["--arena", "moon"],
+        &["--mode", "replay"],
+        &["--seed"],
+        &["--speed", "11"],
+    ] {
+        assert!(parse(args).is_err(), "{args:?} was accepted");
+    }
+}
+
+#[test]
+fn unplayable_options_are_rejected() {
+    for args in [
+        &["--window", "0x480"][..],
+        &["--players", "0"],
+        &["--players", "5"],
+        &["--match-length", "0"],
+        &["--headless"],
+        &["--headless", "--match-length", "60", "--mode", "editor"],
+    ] {
+        assert!(
+            matches!(parse(args), Err(ConfigError::Invalid(_))),
+            "{args:?} was accepted"
+        );
+    }
+}
+
+#[test]
+fn arenas_from_the_editor_are_played_from_their_file() {
+    let path = std::env::temp_dir().join(format!("bevy_soccer-arena-{}.ron", process::id()));
+    fs::write(&path, "(goals: [], obstacles: [])").unwrap();
+
+    let config = parse(&["--arena", path.to_str().unwrap()]).unwrap();
+    assert_eq!(config.arena, Arena::File(path.clone()));
+
+    let mut game = HeadlessApp::with_config(&config);
+    assert_eq!(
+        *game.world().resource::<ObstacleLayout>(),
+        ObstacleLayout::File(path)
+    );
+    let world = game.world();
+    assert_eq!(
+        world
+            .query_filtered::<(), With<ObstacleVariant>>()
+            .iter(world)
+            .count(),
+        0
+    );
+    assert_eq!(
+        world.query_filtered::<(), With<Goal>>().iter(world).count(),
+        0
+    );
+}
+
+#[test]
+fn small_windows_are_accepted() {
+    assert_eq!(parse(&["--window", "640x320"]).unwrap().window, (640, 320));
+}
+
+#[test]
+fn config_files_fill_in_the_rest() {
+    let config: LaunchConfig = ron::from_str("(players_per_team: 2, seed: Some(7))").unwrap();
+    assert_eq!(config.players_per_team, 2);
+    assert_eq!(config.seed, Some(7));
+    assert_eq!(config.window, LaunchConfig::default().window);
+
+    assert!(ron::from_str::<LaunchConfig>("(player_per_team: 2)").is_err());
+}
+
+#[test]
+fn team_size_sets_the_line_up() {
+    let config = LaunchConfig {
+        players_per_team: 2,
+        ..LaunchConfig::default()
+    };
+    let mut game = HeadlessApp::with_config(&config);
+
+    assert_eq!(*game.world().resource::<TeamSize>(), TeamSize(2));
+    assert_eq!(count_players(&mut game), 2);
+}
+
+#[test]
+fn versus_puts_both_teams_on_the_pitch() {
+    let config = LaunchConfig {
+        players_per_team: 2,
+        versus: true,
+        ..LaunchConfig::default()
+    };
+    let mut game = HeadlessApp::with_config(&config);
+
+    assert_eq!(count_players(&mut game), 4);
+}
+
+fn count_players(game: &mut HeadlessApp) -> usize {
+    game.world()
+        .query_filtered::<(), With<Player>>()
+        .iter(&game.app.world)
+        .count()
+}
+
+#[test]
+fn match_ends_at_full_time() {
+    let config = LaunchConfig {
+        match_length: Some(2),
+        ..LaunchConfig::default()
+    };
+    let mut game = HeadlessApp::with_config(&config);
+    game.record_events::<FullTime>();
+    game.play_to_full_time();
+    game.step_seconds(1.);
+
+    assert_eq!(game.events::<FullTime>().len(), 1);
+    let clock = game.world().resource::<MatchClock>();
+    assert!(clock.is_full_time());
+    assert_eq!(clock.elapsed, Duration::from_secs(2));
+}
@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_soccer::{
+    arena::GoalEvent,
+    ball::Ball,
+    constants::*,
+    headless::HeadlessApp,
+    obstacle::{Obstacle, ObstacleVariant},
+    save::SaveData,
+};
+
+/// A match with no obstacles in the way, recording goals.
+fn empty_pitch() -> HeadlessApp {
+    let mut game = HeadlessApp::new();
+    game.clear_obstacles();
+    game.record_events::<GoalEvent>();
+    game
+}
+
+fn ball_position(game: &mut HeadlessApp) -> Vec2 {
+    let ball = game.ball();
+    game.world()
+        .get::<Transform>(ball)
+        .unwrap()
+        .translation
+        .truncate()
+}
+
+fn ball_velocity(game: &mut HeadlessApp) -> Vec2 {
+    let ball = game.ball();
+    game.world().get::<Velocity>(ball).unwrap().linvel
+}
+
+fn place(game: &mut HeadlessApp, x: f32, y: f32, variant: ObstacleVariant) -> Entity {
+    game.spawn_obstacle(Obstacle {
+        translation: Vec3::new(x, y, 4.),
+        rotation: 0.,
+        variant,
+    })
+}
+
+#[test]
+fn ball_into_right_goal_scores_exactly_once() {
+    let mut game = empty_pitch();
+    game.kick_ball(Vec2::new(300., GROUND_MIDDLE), Vec2::new(400., 0.));
+    game.step_seconds(2.);
+
+    let goals = game.events::<GoalEvent>();
+    assert_eq!(goals.len(), 1);
+
+    let goal = goals[0].goal;
+    let goal_x = game.world().get::<Transform>(goal).unwrap().translation.x;
+    assert!(goal_x > 0., "scored in the goal at x = {goal_x}");
+}
+
+#[test]
+fn ball_into_left_goal_scores_exactly_once() {
+    let mut game = empty_pitch();
+    game.kick_ball(Vec2::new(-300., GROUND_MIDDLE), Vec2::new(-400., 0.));
+    game.step_seconds(2.);
+
+    let goals = game.events::<GoalEvent>();
+    assert_eq!(goals.len(), 1);
+
+    let goal = goals[0].goal;
+    let goal_x = game.world().get::<Transform>(goal).unwrap().translation.x;
+    assert!(goal_x < 0., "scored in the goal at x = {goal_x}");
+}
+
+#[test]
+fn goal_adds_to_the_score() {
+    let mut game = empty_pitch();
+    game.kick_ball(Vec2::new(300., GROUND_MIDDLE), Vec2::new(400., 0.));
+    game.step_seconds(2.);
+
+    assert_eq!(game.world().resource::<SaveData>().score, 1);
+}
+
+#[test]
+fn ball_returns_to_kickoff_after_a_goal() {
+    let mut game = empty_pitch();
+    game.kick_ball(Vec2::new(300., GROUND_MIDDLE), Vec2::new(400., 0.));
+    game.step_seconds(2.);
+
+    assert_eq!(game.events::<GoalEvent>().len(), 1);
+    assert_eq!(ball_position(&mut game), Vec2::new(0., GROUND_MIDDLE));
+    assert_eq!(ball_velocity(&mut game), Vec2::ZERO);
+}
+
+#[test]
+fn still_ball_does_not_score() {
+    let mut game = empty_pitch();
+    game.step_seconds(2.);
+
+    assert!(game.events::<GoalEvent>().is_empty());
+}
+
+#[test]
+fn ball_bounces_off_the_top_wall() {
+    let mut game = empty_pitch();
+    game.kick_ball(Vec2::new(0., 120.), Vec2::new(0., 300.));
+    game.step_seconds(1.);
+
+    assert!(ball_velocity(&mut game).y < 0.);
+    assert!(ball_position(&mut game).y < VIRTUAL_HEIGHT / 2.);
+}
+
+#[test]
+fn ball_stays_between_the_walls() {
+    let mut game = empty_pitch();
+    game.kick_ball(Vec2::new(0., GROUND_MIDDLE), Vec2::new(0., 600.));
+
+    let bottom = -VIRTUAL_HEIGHT / 2. + GROUND_OFFSET.y * 2.;
+    for _ in 0..5 {
+        game.step_seconds(1.);
+        let position = ball_position(&mut game);
+        assert!(
+            position.y > bottom && position.y < VIRTUAL_HEIGHT / 2.,
+            "ball escaped to {position}"
+        );
+    }
+}
+
+#[test]
+fn static_obstacle_sends_the_ball_back() {
+    let mut game = empty_pitch();
+    place(&mut game, 100., GROUND_MIDDLE, ObstacleVariant::Static);
+    game.kick_ball(Vec2::new(0., GROUND_MIDDLE), Vec2::new(300., 0.));
+    game.step_seconds(0.5);
+
+    assert!(ball_velocity(&mut game).x < 0.);
+}
+
+#[test]
+fn bumper_speeds_the_ball_up() {
+    let mut game = empty_pitch();
+    place(
+        &mut game,
+        100.,
+        GROUND_MIDDLE,
+        ObstacleVariant::Bumper { strength: 400. },
+    );
+    game.kick_ball(Vec2::new(40., GROUND_MIDDLE), Vec2::new(150., 0.));
+    game.step_seconds(0.5);
+
+    let velocity = ball_velocity(&mut game);
+    assert!(velocity.x < 0.);
+    assert!(velocity.length() > 150. * 1.1, "ball left at {velocity}");
+}
+
+#[test]
+fn breakable_obstacle_breaks_after_its_hits() {
+    let mut game = empty_pitch();
+    let obstacle = place(
+        &mut game,
+        100.,
+        GROUND_MIDDLE,
+        ObstacleVariant::Breakable { hits: 1 },
+    );
+    game.kick_ball(Vec2::new(0., GROUND_MIDDLE), Vec2::new(300., 0.));
+    game.step_seconds(1.);
+
+    assert!(game.world().get_entity(obstacle).is_none());
+}
+
+#[test]
+fn breakable_obstacle_survives_fewer_hits() {
+    let mut game = empty_pitch();
+    let obstacle = place(
+        &mut game,
+        100.,
+        GROUND_MIDDLE,
+        ObstacleVariant::Breakable { hits: 5 },
+    );
+    game.kick_ball(Vec2::new(0., GROUND_MIDDLE), Vec2::new(300., 0.));
+    game.step_seconds(1.);
+
+    assert!(game.world().get_entity(obstacle).is_some());
+}
+
+#[test]
+fn teleporter_moves_the_ball_to_its_pair() {
+    let mut game = empty_pitch();
+    place(
+        &mut game,
+        -100.,
+        GROUND_MIDDLE,
+        ObstacleVariant::Teleporter { pair: 0 },
+    );
+    place(
+        &mut game,
+        100.,
+        170.,
+        ObstacleVariant::Teleporter { pair: 0 },
+    );
+    game.kick_ball(Vec2::new(-150., GROUND_MIDDLE), Vec2::new(200., 0.));
+    game.step_seconds(0.5);
+
+    let position = ball_position(&mut game);
+    assert!(
+        (position.y - 170.).abs() < 1.,
+        "ball ended up at {position}"
+    );
+    assert!(position.x > 100.);
+}
+
+#[test]
+fn same_kick_plays_out_the_same_way() {
+    let run = || {
+        let mut game = HeadlessApp::new();
+        game.kick_ball(Vec2::new(-50., GROUND_MIDDLE), Vec2::new(350., 220.));
+        game.step_seconds(3.);
+        (ball_position(&mut game), ball_velocity(&mut game))
+    };
+
+    assert_eq!(run(), run());
+}
+
+#[test]
+fn only_one_ball_is_in_play() {
+    let mut game = empty_pitch();
+    game.kick_ball(Vec2::new(300., GROUND_MIDDLE), Vec2::new(400., 0.));
+
+    for _ in 0..4 {
+        game.step_seconds(0.5);
+        let balls = game
+            .world()
+            .query_filtered::<Entity, With<Ball>>()
+            .iter(&game.app.world)
+            .count();
+        assert!(balls <= 1);
+    }
+}
@@ -0,0 +1,75 @@
+use std::{fs, process};
+
+use bevy::prelude::*;
+use bevy_soccer::{
+    constants::*,
+    headless::HeadlessApp,
+    heatmap::{Heatmap, Heatmaps},
+    player::{TeamSize, Versus},
+};
+
+fn heatmaps(game: &mut HeadlessApp) -> Heatmaps {
+    game.world().resource::<Heatmaps>().clone()
+}
+
+#[test]
+fn still_ball_heats_up_the_kickoff_spot() {
+    let mut game = HeadlessApp::new();
+    game.clear_obstacles();
+    game.step_seconds(2.);
+
+    let heatmaps = heatmaps(&mut game);
+    let kickoff = Vec2::new(0., GROUND_MIDDLE);
+    assert!(heatmaps.ball.samples() > 0);
+    assert_eq!(heatmaps.ball.count(kickoff), heatmaps.ball.samples());
+}
+
+#[test]
+fn every_player_gets_a_heatmap() {
+    let mut game = HeadlessApp::new();
+    game.step_seconds(1.);
+
+    let team_size = game.world().resource::<TeamSize>().0;
+    let teams = game.world().resource::<Versus>().teams();
+    let heatmaps = heatmaps(&mut game);
+    assert_eq!(heatmaps.players.len(), teams * team_size);
+    assert!(heatmaps
+        .players
+        .values()
+        .all(|heatmap| heatmap.samples() > 0));
+}
+
+#[test]
+fn positions_off_the_ground_count_at_the_edge() {
+    let mut heatmap = Heatmap::default();
+    heatmap.add(Vec2::new(1000., 1000.));
+
+    let corner = Vec2::new(
+        GROUND_WIDTH / 2. - 1.,
+        GROUND_MIDDLE + GROUND_HEIGHT / 2. - 1.,
+    );
+    assert_eq!(heatmap.count(corner), 1);
+    assert_eq!(heatmap.samples(), 1);
+}
+
+#[test]
+fn export_writes_a_png_per_heatmap() {
+    let mut game = HeadlessApp::new();
+    game.step_seconds(1.);
+    let heatmaps = heatmaps(&mut game);
+
+    let dir = std::env::temp_dir().join(format!("bevy_soccer-heatmaps-{}", process::id()));
+    let files = heatmaps.export(&dir).unwrap();
+    assert_eq!(files.len(), 1 + heatmaps.players.len());
+    assert!(files.iter().any(|file| file.ends_with("ball.png")));
+    for file in &files {
+        let png = fs::read(file).unwrap();
+        assert!(
+            png.starts_with(b"\x89PNG"),
+            "{} isn't a PNG",
+            file.display()
+        );
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,97 @@
+use bevy_soccer::{
+    config::LaunchConfig,
+    headless::HeadlessApp,
+    leaderboard::{HighScores, InitialsEntry, MatchResult, HISTORY_SIZE, LEADERBOARD_SIZE},
+    mode::GameMode,
+    obstacle::ObstacleLayout,
+    save::SaveData,
+};
+
+fn result(goals: [u32; 2]) -> MatchResult {
+    MatchResult::new(goals, ObstacleLayout::Classic, GameMode::Match)
+}
+
+#[test]
+fn result_is_taken_from_the_winners_side() {
+    let result = result([1, 4]);
+    assert_eq!(result.goals, 4);
+    assert_eq!(result.margin, 3);
+    assert_eq!(result.score(), "4-1");
+}
+
+#[test]
+fn table_is_ordered_by_goals_then_margin() {
+    let mut high_scores = HighScores::default();
+    assert_eq!(high_scores.record(result([2, 2])), Some(0));
+    assert_eq!(high_scores.record(result([3, 1])), Some(0));
+    assert_eq!(high_scores.record(result([0, 2])), Some(1));
+    assert_eq!(high_scores.record(result([1, 0])), Some(3));
+
+    let scores: Vec<String> = high_scores.table.iter().map(MatchResult::score).collect();
+    assert_eq!(scores, ["3-1", "2-0", "2-2", "1-0"]);
+    assert_eq!(high_scores.best().unwrap().score(), "3-1");
+}
+
+#[test]
+fn goalless_matches_only_go_in_the_history() {
+    let mut high_scores = HighScores::default();
+    assert_eq!(high_scores.record(result([0, 0])), None);
+    assert!(high_scores.table.is_empty());
+    assert_eq!(high_scores.history.len(), 1);
+}
+
+#[test]
+fn table_and_history_are_capped() {
+    let mut high_scores = HighScores::default();
+    for goals in 1..=30 {
+        high_scores.record(result([goals, 0]));
+    }
+
+    assert_eq!(high_scores.table.len(), LEADERBOARD_SIZE);
+    assert_eq!(high_scores.best().unwrap().goals, 30);
+    assert_eq!(high_scores.history.len(), HISTORY_SIZE);
+    assert_eq!(high_scores.history[0].goals, 30);
+    assert!(!high_scores.qualifies(&result([1, 0])));
+    assert!(high_scores.qualifies(&result([25, 0])));
+}
+
+#[test]
+fn day_is_formatted_in_utc() {
+    let mut result = result([1, 0]);
+    result.date = 1_709_251_199;
+    assert_eq!(result.day(), "2024-02-29");
+    result.date = 0;
+    assert_eq!(result.day(), "1970-01-01");
+}
+
+#[test]
+fn record_result_asks_for_initials_at_full_time() {
+    let mut game = HeadlessApp::timed_match(2);
+    game.score_for(0);
+    game.play_to_full_time();
+    game.step(1);
+
+    assert!(game.world().resource::<InitialsEntry>().is_active());
+    assert!(game
+        .world()
+        .resource::<SaveData>()
+        .high_scores
+        .table
+        .is_empty());
+}
+
+#[test]
+fn goalless_draw_goes_straight_into_the_history() {
+    let config = LaunchConfig {
+        match_length: Some(1),
+        ..LaunchConfig::default()
+    };
+    let mut game = HeadlessApp::with_config(&config);
+    game.play_to_full_time();
+    game.step(1);
+
+    assert!(!game.world().resource::<InitialsEntry>().is_active());
+    let high_scores = &game.world().resource::<SaveData>().high_scores;
+    assert_eq!(high_scores.history.len(), 1);
+    assert_eq!(high_scores.history[0].score(), "0-0");
+}
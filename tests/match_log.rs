@@ -0,0 +1,106 @@
+use std::{fs, path::PathBuf, process};
+
+use bevy::prelude::*;
+use bevy_soccer::{
+    config::LaunchConfig,
+    constants::*,
+    headless::HeadlessApp,
+    match_log::{LogEntry, MatchEvent, PlayerId},
+};
+
+fn log_dir(test: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("bevy_soccer-log-{}-{test}", process::id()))
+}
+
+/// The entries of the only log file in `dir`.
+fn read_log(dir: &PathBuf) -> Vec<LogEntry> {
+    let files: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 1, "expected one log, found {files:?}");
+
+    fs::read_to_string(&files[0])
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn logged_match(dir: &PathBuf) -> HeadlessApp {
+    let _ = fs::remove_dir_all(dir);
+    let config = LaunchConfig {
+        match_length: Some(3),
+        event_log: Some(dir.clone()),
+        ..LaunchConfig::default()
+    };
+    let mut game = HeadlessApp::with_config(&config);
+    game.clear_obstacles();
+    game
+}
+
+#[test]
+fn match_is_logged_from_kickoff_to_full_time() {
+    let dir = log_dir("full_match");
+    let mut game = logged_match(&dir);
+    game.score_for(0);
+    game.play_to_full_time();
+
+    let entries = read_log(&dir);
+    let events: Vec<&MatchEvent> = entries.iter().map(|entry| &entry.event).collect();
+    assert_eq!(events.first(), Some(&&MatchEvent::Kickoff));
+    assert_eq!(events.last(), Some(&&MatchEvent::PeriodEnd { period: 1 }));
+    assert!(events.contains(&&MatchEvent::Goal {
+        team: 0,
+        scorer: None
+    }));
+    assert_eq!(
+        events
+            .iter()
+            .filter(|event| ***event == MatchEvent::Kickoff)
+            .count(),
+        2,
+        "kicks off again after the goal"
+    );
+
+    let ticks: Vec<u32> = entries.iter().map(|entry| entry.tick).collect();
+    assert!(ticks.windows(2).all(|pair| pair[0] <= pair[1]), "{ticks:?}");
+    assert_eq!(entries.last().unwrap().tick, 3 * 64);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn wall_bounces_are_logged_where_they_happen() {
+    let dir = log_dir("wall_bounce");
+    let mut game = logged_match(&dir);
+    game.kick_ball(Vec2::new(0., GROUND_MIDDLE), Vec2::new(0., 400.));
+    game.play_to_full_time();
+
+    let bounce = read_log(&dir)
+        .into_iter()
+        .find(|entry| entry.event == MatchEvent::WallBounce)
+        .expect("no wall bounce logged");
+    let [_, y] = bounce.position.unwrap();
+    assert!(y > 150., "bounced at y = {y}");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn schema_is_stable() {
+    let entry = LogEntry {
+        tick: 12,
+        position: Some([1.5, -2.]),
+        event: MatchEvent::Shot {
+            player: PlayerId { team: 1, number: 0 },
+            on_target: true,
+        },
+    };
+    let line = serde_json::to_string(&entry).unwrap();
+    assert_eq!(
+        line,
+        r#"{"tick":12,"position":[1.5,-2.0],"event":"shot","player":{"team":1,"number":0},"on_target":true}"#
+    );
+    assert_eq!(serde_json::from_str::<LogEntry>(&line).unwrap(), entry);
+}
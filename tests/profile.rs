@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use bevy_soccer::{
+    headless::HeadlessApp,
+    player::{Bindings, Versus},
+    profile::{Profile, ProfileStats},
+    save::SaveData,
+};
+
+#[test]
+fn stats_count_results_and_records() {
+    let mut stats = ProfileStats::default();
+    stats.record(3, 1);
+    stats.record(0, 2);
+    stats.record(1, 1);
+    stats.record(2, 0);
+
+    assert_eq!(
+        (stats.played, stats.won, stats.drawn, stats.lost),
+        (4, 2, 1, 1)
+    );
+    assert_eq!((stats.goals_for, stats.goals_against), (6, 4));
+    assert_eq!(stats.most_goals, 3);
+    assert_eq!(stats.biggest_win, 2);
+}
+
+#[test]
+fn initials_come_from_the_name() {
+    assert_eq!(Profile::new("sam o'neil").initials(), "SAM");
+    assert_eq!(Profile::new("Jo").initials(), "JO");
+}
+
+#[test]
+fn changing_kit_takes_its_keys_unless_rebound() {
+    let mut profile = Profile::new("Sam");
+    profile.change_kit();
+    assert_eq!(profile.kit, 1);
+    assert_eq!(profile.bindings, Bindings::for_team(1));
+
+    profile.bindings.run = KeyCode::Space;
+    profile.change_kit();
+    assert_eq!(profile.kit, 0);
+    assert_eq!(profile.bindings.run, KeyCode::Space);
+}
+
+#[test]
+fn only_the_active_profiles_team_gets_their_keys() {
+    let mut profile = Profile::new("Sam");
+    profile.bindings.up = KeyCode::KeyI;
+    let mut save = SaveData {
+        profiles: vec![profile],
+        ..SaveData::default()
+    };
+    let versus = Versus(true);
+    assert_eq!(save.bindings(0, versus), Bindings::for_team(0));
+
+    save.active_profile = Some(0);
+    assert_eq!(save.bindings(0, versus).up, KeyCode::KeyI);
+    assert_eq!(save.bindings(1, versus), Bindings::for_team(1));
+}
+
+#[test]
+fn solo_play_is_on_the_only_team_whatever_the_kit() {
+    let mut game = HeadlessApp::timed_match(2);
+    {
+        let mut save = game.world().resource_mut::<SaveData>();
+        let mut profile = Profile::new("Sam");
+        profile.change_kit();
+        profile.bindings.up = KeyCode::KeyI;
+        save.profiles.push(profile);
+        save.active_profile = Some(0);
+    }
+    game.score_for(0);
+    game.play_to_full_time();
+    game.step(1);
+
+    let save = game.world().resource::<SaveData>();
+    assert_eq!(save.bindings(0, Versus(false)).up, KeyCode::KeyI);
+    let stats = &save.profiles[0].stats;
+    assert_eq!((stats.played, stats.won, stats.lost), (1, 1, 0));
+    assert_eq!((stats.goals_for, stats.goals_against), (1, 0));
+}
+
+#[test]
+fn results_count_for_the_active_profile() {
+    let mut game = HeadlessApp::timed_match(2);
+    {
+        let mut save = game.world().resource_mut::<SaveData>();
+        save.profiles.push(Profile::new("Sam"));
+        save.active_profile = Some(0);
+    }
+    game.score_for(0);
+    game.play_to_full_time();
+    game.step(1);
+
+    let save = game.world().resource::<SaveData>();
+    let stats = &save.profiles[0].stats;
+    assert_eq!((stats.played, stats.won), (1, 1));
+    assert_eq!(stats.goals_for, 1);
+}
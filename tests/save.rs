@@ -0,0 +1,94 @@
+use std::{
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use bevy_pkv::PkvStore;
+use bevy_soccer::{
+    config::LaunchConfig,
+    headless::HeadlessApp,
+    leaderboard::MatchResult,
+    mode::GameMode,
+    obstacle::ObstacleLayout,
+    save::{SaveData, SAVE_KEY, SAVE_VERSION},
+};
+
+fn empty_store() -> PkvStore {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "bevy_soccer-save-{}-{}",
+        process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    PkvStore::new_in_dir(dir)
+}
+
+#[test]
+fn nothing_saved_loads_the_defaults() {
+    let save = SaveData::load(&empty_store());
+    assert_eq!(save, SaveData::default());
+    assert_eq!(save.version, SAVE_VERSION);
+}
+
+#[test]
+fn legacy_keys_are_migrated() {
+    let mut pkv = empty_store();
+    pkv.set("score", &2).unwrap();
+    pkv.set("high_score", &7).unwrap();
+
+    let save = SaveData::load(&pkv);
+    assert_eq!(save.version, SAVE_VERSION);
+    assert_eq!(save.score, 2);
+    let best = save.high_scores.best().unwrap();
+    assert_eq!(best.initials, "???");
+    assert_eq!(best.goals, 7);
+}
+
+#[test]
+fn save_round_trips() {
+    let mut pkv = empty_store();
+    let mut save = SaveData {
+        score: 5,
+        ..SaveData::default()
+    };
+    save.high_scores.record(MatchResult::new(
+        [2, 0],
+        ObstacleLayout::Classic,
+        GameMode::Match,
+    ));
+    save.write(&mut pkv);
+
+    assert_eq!(SaveData::load(&pkv), save);
+}
+
+#[test]
+fn saves_from_a_newer_game_are_not_read() {
+    let mut pkv = empty_store();
+    SaveData {
+        version: SAVE_VERSION + 1,
+        score: 3,
+        ..SaveData::default()
+    }
+    .write(&mut pkv);
+
+    assert_eq!(SaveData::load(&pkv), SaveData::default());
+
+    let mut game = HeadlessApp::with_store(&LaunchConfig::default(), pkv);
+    game.step(1);
+    game.clear_obstacles();
+    game.score_for(0);
+
+    let stored: SaveData = game.world().resource::<PkvStore>().get(SAVE_KEY).unwrap();
+    assert_eq!(stored.version, SAVE_VERSION + 1, "the newer save was kept");
+    assert_eq!(stored.score, 3);
+}
+
+#[test]
+fn goals_are_saved_as_they_go_in() {
+    let mut game = HeadlessApp::new();
+    game.clear_obstacles();
+    game.score_for(0);
+
+    let stored = SaveData::load(game.world().resource::<PkvStore>());
+    assert_eq!(stored.score, 1);
+}
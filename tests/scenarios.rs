@@ -0,0 +1,30 @@
+use std::{fs, path::Path};
+
+use bevy_soccer::scenario::Scenario;
+
+/// Runs every scenario in `tests/scenarios`, reporting all the failures
+/// together rather than stopping at the first.
+#[test]
+fn scenarios() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .expect("Failed to read the scenarios")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no scenarios in {}", dir.display());
+
+    let failures: Vec<String> = paths
+        .iter()
+        .filter_map(|path| {
+            let scenario = match Scenario::load(path) {
+                Ok(scenario) => scenario,
+                Err(error) => return Some(format!("{}: {error}", path.display())),
+            };
+            scenario.run().err().map(|failure| failure.to_string())
+        })
+        .collect();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_soccer::{
+    config::LaunchConfig,
+    headless::HeadlessApp,
+    mode::GameMode,
+    obstacle::ObstacleVariant,
+    profile::Profile,
+    save::SaveData,
+    shootout::{Shootout, ShootoutScore, PENALTY_SPOT, ROUNDS},
+    stats::MatchStats,
+};
+
+fn score(kicks: [&[bool]; 2]) -> ShootoutScore {
+    ShootoutScore {
+        kicks: kicks.map(<[bool]>::to_vec),
+    }
+}
+
+#[test]
+fn teams_take_turns_blue_first() {
+    let mut score = ShootoutScore::default();
+    assert_eq!(score.kicking_team(), 0);
+    score.record(true);
+    assert_eq!(score.kicking_team(), 1);
+    score.record(false);
+    assert_eq!(score.kicking_team(), 0);
+    assert_eq!(score.goals(), [1, 0]);
+}
+
+#[test]
+fn shootout_ends_once_it_cant_be_caught() {
+    // 3-0 after three kicks each: Red's last two can't make up three goals.
+    let early = score([&[true, true, true], &[false, false, false]]);
+    assert_eq!(early.winner(), Some(0));
+
+    // 3-0 with Red still to take their third: two more would only draw.
+    let still_open = score([&[true, true, true], &[false, false]]);
+    assert_eq!(still_open.winner(), None);
+
+    let close = score([&[true; ROUNDS], &[true, true, true, true, false]]);
+    assert_eq!(close.winner(), Some(0));
+}
+
+#[test]
+fn sudden_death_needs_a_full_round() {
+    let level = score([&[true; ROUNDS], &[true; ROUNDS]]);
+    assert_eq!(level.winner(), None);
+
+    let mut sudden_death = level.clone();
+    sudden_death.record(false);
+    assert_eq!(sudden_death.winner(), None);
+    sudden_death.record(true);
+    assert_eq!(sudden_death.winner(), Some(1));
+}
+
+fn start_shootout() -> HeadlessApp {
+    let mut game = HeadlessApp::new();
+    game.world()
+        .resource_mut::<NextState<GameMode>>()
+        .set(GameMode::Shootout);
+    game.step(2);
+    game
+}
+
+fn hold(game: &mut HeadlessApp, key: KeyCode, ticks: usize) {
+    game.world()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(key);
+    game.step(ticks);
+    game.world()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .release(key);
+}
+
+fn blue_kicks(game: &mut HeadlessApp) -> Vec<bool> {
+    game.world().resource::<Shootout>().score.kicks[0].clone()
+}
+
+#[test]
+fn ball_waits_on_the_spot() {
+    let mut game = start_shootout();
+    let ball = game.ball();
+    let position = game.world().get::<Transform>(ball).unwrap().translation;
+    assert!(position.truncate().distance(PENALTY_SPOT) < 1.);
+}
+
+#[test]
+fn a_shot_straight_at_the_keeper_is_saved() {
+    let mut game = start_shootout();
+    hold(&mut game, KeyCode::ShiftLeft, 32);
+    game.step_seconds(2.);
+
+    assert_eq!(blue_kicks(&mut game), [false]);
+}
+
+#[test]
+fn a_shot_into_the_corner_goes_in() {
+    let mut game = start_shootout();
+    hold(&mut game, KeyCode::KeyW, 38);
+    hold(&mut game, KeyCode::ShiftLeft, 64);
+    game.step_seconds(2.);
+
+    assert_eq!(blue_kicks(&mut game), [true]);
+}
+
+#[test]
+fn launching_into_a_shootout_clears_the_obstacles() {
+    let mut game = HeadlessApp::with_config(&LaunchConfig {
+        mode: GameMode::Shootout,
+        ..LaunchConfig::default()
+    });
+    game.step(1);
+
+    let world = game.world();
+    let mut obstacles = world.query_filtered::<Has<ColliderDisabled>, With<ObstacleVariant>>();
+    let disabled: Vec<bool> = obstacles.iter(world).collect();
+    assert!(!disabled.is_empty());
+    assert!(disabled.iter().all(|&disabled| disabled));
+}
+
+#[test]
+fn tie_breaker_winner_is_kept_with_the_match() {
+    let mut game = HeadlessApp::timed_match(1);
+    let mut save = game.world().resource_mut::<SaveData>();
+    save.profiles = vec![Profile::new("Sam")];
+    save.active_profile = Some(0);
+    game.play_to_full_time();
+    game.step(1);
+    hold(&mut game, KeyCode::KeyS, 2);
+    assert_eq!(
+        *game.world().resource::<State<GameMode>>().get(),
+        GameMode::Shootout
+    );
+
+    game.world().resource_mut::<Shootout>().score =
+        score([&[true, true, true], &[false, false, false]]);
+    game.world()
+        .resource_mut::<NextState<GameMode>>()
+        .set(GameMode::Match);
+    game.step(2);
+
+    assert_eq!(
+        game.world().resource::<MatchStats>().penalties,
+        Some([3, 0])
+    );
+    let latest = &game.world().resource::<SaveData>().high_scores.history[0];
+    assert_eq!(latest.penalties, Some((3, 0)));
+    assert_eq!(latest.score(), "0-0 (3-0 pens)");
+
+    // Settled, so there's no second shootout for the same match.
+    hold(&mut game, KeyCode::KeyS, 2);
+    assert_eq!(
+        *game.world().resource::<State<GameMode>>().get(),
+        GameMode::Match
+    );
+    let stats = &game.world().resource::<SaveData>().profiles[0].stats;
+    assert_eq!(stats.shootouts_won + stats.shootouts_lost, 1);
+}
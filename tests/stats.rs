@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+use bevy_soccer::{
+    ball::Ball,
+    constants::*,
+    headless::HeadlessApp,
+    mode::GameMode,
+    obstacle::{Obstacle, ObstacleVariant},
+    replay::ReplayPlugin,
+    save::SaveData,
+    stats::MatchStats,
+};
+
+fn stats(game: &mut HeadlessApp) -> MatchStats {
+    game.world().resource::<MatchStats>().clone()
+}
+
+#[test]
+fn goal_is_recorded_for_the_attacking_team() {
+    let mut game = HeadlessApp::new();
+    game.clear_obstacles();
+    game.step_seconds(1.);
+    game.score_for(0);
+
+    let stats = stats(&mut game);
+    assert_eq!(stats.goals.len(), 1);
+    assert_eq!(stats.goals[0].team, 0);
+    assert_eq!(stats.goals[0].scorer, None);
+    assert!(
+        stats.goals[0].time >= 1.,
+        "scored at {}s",
+        stats.goals[0].time
+    );
+    assert_eq!(stats.teams[0].goals, 1);
+    assert_eq!(stats.teams[1].goals, 0);
+}
+
+#[test]
+fn ball_off_an_obstacle_counts_as_a_deflection() {
+    let mut game = HeadlessApp::new();
+    game.clear_obstacles();
+    game.spawn_obstacle(Obstacle {
+        translation: Vec3::new(0., GROUND_MIDDLE + 100., 4.),
+        rotation: 0.,
+        variant: ObstacleVariant::Static,
+    });
+    game.step(1);
+    game.kick_ball(Vec2::new(0., GROUND_MIDDLE), Vec2::new(0., 300.));
+    game.step_seconds(0.5);
+
+    assert_eq!(stats(&mut game).deflections, 1);
+}
+
+#[test]
+fn nobody_has_possession_before_a_touch() {
+    let mut game = HeadlessApp::new();
+    game.clear_obstacles();
+    game.step_seconds(1.);
+
+    let stats = stats(&mut game);
+    assert_eq!(stats.teams[0].possession, 0.);
+    assert_eq!(stats.teams[1].possession, 0.);
+    assert_eq!(stats.possession_share(), [0.5, 0.5]);
+}
+
+fn ball_positions(game: &mut HeadlessApp) -> Vec<Vec3> {
+    let world = game.world();
+    let mut balls = world.query_filtered::<&Transform, With<Ball>>();
+    balls.iter(world).map(|ball| ball.translation).collect()
+}
+
+#[test]
+fn a_late_goal_leaves_the_match_frozen_at_full_time() {
+    let mut game = HeadlessApp::timed_match(3);
+    game.app.add_plugins(ReplayPlugin);
+    game.step_seconds(1.5);
+    // Goes in with little more than a second left, too late for its replay.
+    game.score_for(0);
+    game.play_to_full_time();
+
+    let frozen = stats(&mut game);
+    let score = game.world().resource::<SaveData>().score;
+    let balls = ball_positions(&mut game);
+    game.step_seconds(12.);
+
+    assert_eq!(
+        *game.world().resource::<State<GameMode>>().get(),
+        GameMode::Match
+    );
+    assert!(game.world().resource::<Time<Virtual>>().is_paused());
+    assert_eq!(stats(&mut game), frozen);
+    assert_eq!(game.world().resource::<SaveData>().score, score);
+    assert_eq!(ball_positions(&mut game), balls);
+}
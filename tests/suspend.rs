@@ -0,0 +1,161 @@
+use bevy::{app::AppExit, prelude::*};
+use bevy_pkv::PkvStore;
+use bevy_soccer::{
+    clock::MatchClock,
+    config::LaunchConfig,
+    headless::HeadlessApp,
+    mode::GameMode,
+    recording::RecordingPlugin,
+    replay::ReplayPlugin,
+    save::SaveData,
+    stats::MatchStats,
+    suspend::{ContinueChoice, SuspendedMatch},
+};
+
+const RESTING_PLACE: Vec2 = Vec2::new(50., 120.);
+
+fn ball_position(game: &mut HeadlessApp) -> Vec2 {
+    let ball = game.ball();
+    game.world()
+        .get::<Transform>(ball)
+        .unwrap()
+        .translation
+        .truncate()
+}
+
+/// Scores once, leaves the ball resting away from everyone, then closes
+/// the game and hands back what was saved.
+fn suspend_after_a_goal() -> SuspendedMatch {
+    let mut game = HeadlessApp::timed_match(60);
+    game.score_for(0);
+    game.kick_ball(RESTING_PLACE, Vec2::ZERO);
+    game.step(2);
+
+    game.world().send_event(AppExit);
+    game.step(1);
+
+    let stored = SaveData::load(game.world().resource::<PkvStore>());
+    assert_eq!(stored.score, 0, "the score is kept with the match instead");
+    stored
+        .suspended
+        .expect("the match should have been suspended")
+}
+
+#[test]
+fn closing_mid_match_suspends_it() {
+    let suspended = suspend_after_a_goal();
+    assert_eq!(suspended.stats().teams[0].goals, 1);
+    assert!(suspended.elapsed().as_secs_f32() >= 2.);
+}
+
+#[test]
+fn continuing_puts_the_match_back() {
+    let suspended = suspend_after_a_goal();
+    let elapsed = suspended.elapsed();
+
+    let mut game = HeadlessApp::timed_match(60);
+    game.world().resource_mut::<SaveData>().suspended = Some(suspended);
+    game.world().send_event(ContinueChoice::Continue);
+    game.step(1);
+
+    let save = game.world().resource::<SaveData>();
+    assert_eq!(save.score, 1);
+    assert!(save.suspended.is_none());
+    assert_eq!(game.world().resource::<MatchStats>().teams[0].goals, 1);
+    assert!(game.world().resource::<MatchClock>().elapsed >= elapsed);
+    assert!(ball_position(&mut game).distance(RESTING_PLACE) < 1.);
+}
+
+#[test]
+fn a_new_match_drops_the_suspended_one() {
+    let suspended = suspend_after_a_goal();
+
+    let mut game = HeadlessApp::timed_match(60);
+    game.world().resource_mut::<SaveData>().suspended = Some(suspended);
+    game.world().send_event(ContinueChoice::NewMatch);
+    game.step(1);
+
+    let save = game.world().resource::<SaveData>();
+    assert_eq!(save.score, 0);
+    assert!(save.suspended.is_none());
+}
+
+#[test]
+fn finished_matches_are_not_suspended() {
+    let config = LaunchConfig {
+        match_length: Some(1),
+        ..LaunchConfig::default()
+    };
+    let mut game = HeadlessApp::with_config(&config);
+    game.play_to_full_time();
+    game.world().send_event(AppExit);
+    game.step(1);
+
+    assert!(game.world().resource::<SaveData>().suspended.is_none());
+}
+
+#[test]
+fn closing_during_a_replay_suspends_the_match() {
+    let mut game = HeadlessApp::timed_match(60);
+    game.app.add_plugins(ReplayPlugin);
+    game.score_for(0);
+    assert_eq!(
+        *game.world().resource::<State<GameMode>>().get(),
+        GameMode::Replay
+    );
+
+    game.world().send_event(AppExit);
+    game.step(1);
+
+    let suspended = SaveData::load(game.world().resource::<PkvStore>())
+        .suspended
+        .expect("the match should have been suspended");
+    assert_eq!(suspended.stats().teams[0].goals, 1);
+}
+
+#[test]
+fn closing_in_the_viewer_suspends_the_live_match() {
+    let mut game = HeadlessApp::timed_match(60);
+    game.app.add_plugins(RecordingPlugin);
+    game.score_for(0);
+    game.kick_ball(RESTING_PLACE, Vec2::ZERO);
+    game.step(2);
+    game.watch_recording();
+    assert_eq!(
+        *game.world().resource::<State<GameMode>>().get(),
+        GameMode::Viewer
+    );
+
+    game.world().send_event(AppExit);
+    game.step(1);
+    let suspended = SaveData::load(game.world().resource::<PkvStore>())
+        .suspended
+        .expect("the match should have been suspended");
+    assert_eq!(suspended.stats().teams[0].goals, 1);
+
+    // It's the live match that comes back, not the recording's kickoff.
+    let mut game = HeadlessApp::timed_match(60);
+    game.world().resource_mut::<SaveData>().suspended = Some(suspended);
+    game.world().send_event(ContinueChoice::Continue);
+    game.step(1);
+    assert_eq!(game.world().resource::<SaveData>().score, 1);
+    assert!(ball_position(&mut game).distance(RESTING_PLACE) < 1.);
+}
+
+#[test]
+fn other_modes_leave_the_suspended_match_waiting() {
+    let suspended = suspend_after_a_goal();
+
+    let mut game = HeadlessApp::with_config(&LaunchConfig {
+        mode: GameMode::Shootout,
+        ..LaunchConfig::default()
+    });
+    game.world().resource_mut::<SaveData>().suspended = Some(suspended.clone());
+    game.step(2);
+    assert!(!game.world().resource::<Time<Virtual>>().is_paused());
+
+    game.world().send_event(AppExit);
+    game.step(1);
+    let stored = SaveData::load(game.world().resource::<PkvStore>());
+    assert_eq!(stored.suspended, Some(suspended));
+}